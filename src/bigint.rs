@@ -0,0 +1,422 @@
+use std::{
+    cmp::Ordering,
+    convert::TryFrom,
+    fmt,
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+fn trim(mut magnitude: Vec<u32>) -> Vec<u32> {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+    magnitude
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+
+    for i in 0..a.len().max(b.len()) {
+        let digit = u64::from(*a.get(i).unwrap_or(&0)) + u64::from(*b.get(i).unwrap_or(&0)) + carry;
+        sum.push(digit as u32);
+        carry = digit >> 32;
+    }
+
+    sum.push(carry as u32);
+    trim(sum)
+}
+
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+
+    for (i, &x) in a.iter().enumerate() {
+        let digit = i64::from(x) - i64::from(*b.get(i).unwrap_or(&0)) - borrow;
+        difference.push(digit.rem_euclid(1 << 32) as u32);
+        borrow = if digit < 0 { 1 } else { 0 };
+    }
+
+    trim(difference)
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut product = vec![0u32; a.len() + b.len()];
+
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, &y) in b.iter().enumerate() {
+            let digit = u64::from(x) * u64::from(y) + u64::from(product[i + j]) + carry;
+            product[i + j] = digit as u32;
+            carry = digit >> 32;
+        }
+        product[i + b.len()] = carry as u32;
+    }
+
+    trim(product)
+}
+
+fn div_rem_small(a: &[u32], b: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder = 0u64;
+
+    for i in (0..a.len()).rev() {
+        let digit = remainder << 32 | u64::from(a[i]);
+        quotient[i] = (digit / u64::from(b)) as u32;
+        remainder = digit % u64::from(b);
+    }
+
+    (trim(quotient), remainder as u32)
+}
+
+fn div_rem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if let [divisor] = *b {
+        let (quotient, remainder) = div_rem_small(a, divisor);
+        return (quotient, trim(vec![remainder]));
+    }
+
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder = Vec::new();
+
+    for bit in (0..a.len() * 32).rev() {
+        remainder = shift_left_one(&remainder, a[bit / 32] >> (bit % 32) & 1);
+
+        if cmp_magnitude(&remainder, b) != Ordering::Less {
+            remainder = sub_magnitude(&remainder, b);
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+
+    (trim(quotient), remainder)
+}
+
+fn shift_left_one(magnitude: &[u32], low_bit: u32) -> Vec<u32> {
+    let mut shifted = Vec::with_capacity(magnitude.len() + 1);
+    let mut carry = low_bit;
+
+    for &digit in magnitude {
+        shifted.push(digit << 1 | carry);
+        carry = digit >> 31;
+    }
+
+    shifted.push(carry);
+    trim(shifted)
+}
+
+impl BigInt {
+    fn new(negative: bool, magnitude: Vec<u32>) -> Self {
+        let magnitude = trim(magnitude);
+        Self {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_even(&self) -> bool {
+        self.magnitude.first().copied().unwrap_or(0) & 1 == 0
+    }
+
+    pub fn bits(&self) -> u64 {
+        match self.magnitude.last() {
+            None => 0,
+            Some(digit) => self.magnitude.len() as u64 * 32 - u64::from(digit.leading_zeros()),
+        }
+    }
+
+    pub fn to_u32(&self) -> Option<u32> {
+        match self.magnitude[..] {
+            _ if self.negative => None,
+            [] => Some(0),
+            [digit] => Some(digit),
+            _ => None,
+        }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        let magnitude = match self.magnitude[..] {
+            [] => 0,
+            [low] => u64::from(low),
+            [low, high] => u64::from(high) << 32 | u64::from(low),
+            _ => return None,
+        };
+
+        if self.negative {
+            0i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+
+    pub fn div_rem(&self, other: &Self) -> Option<(Self, Self)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let (quotient, remainder) = div_rem_magnitude(&self.magnitude, &other.magnitude);
+
+        Some((
+            Self::new(self.negative != other.negative, quotient),
+            Self::new(self.negative, remainder),
+        ))
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> Self {
+        let mut base = self.clone();
+        let mut result = Self::from(1);
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+            base = &base * &base;
+            exponent >>= 1;
+        }
+
+        result
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(n: i64) -> Self {
+        let magnitude = n.unsigned_abs();
+        Self::new(n < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(());
+        }
+
+        let chunk_len = (digits.len() - 1) % 9 + 1;
+        let (first, rest) = digits.split_at(chunk_len);
+        let mut magnitude = vec![first.parse().unwrap()];
+
+        for chunk in rest.as_bytes().chunks(9) {
+            let chunk = std::str::from_utf8(chunk).unwrap().parse().unwrap();
+            magnitude = add_magnitude(&mul_magnitude(&magnitude, &[1_000_000_000]), &[chunk]);
+        }
+
+        Ok(Self::new(negative, magnitude))
+    }
+}
+
+impl Neg for BigInt {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self::new(!self.negative, self.magnitude)
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: Self) -> Self::Output {
+        if self.negative == other.negative {
+            return BigInt::new(
+                self.negative,
+                add_magnitude(&self.magnitude, &other.magnitude),
+            );
+        }
+
+        match cmp_magnitude(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::new(
+                other.negative,
+                sub_magnitude(&other.magnitude, &self.magnitude),
+            ),
+            _ => BigInt::new(
+                self.negative,
+                sub_magnitude(&self.magnitude, &other.magnitude),
+            ),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: Self) -> Self::Output {
+        self + &-other.clone()
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: Self) -> Self::Output {
+        BigInt::new(
+            self.negative != other.negative,
+            mul_magnitude(&self.magnitude, &other.magnitude),
+        )
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const CHUNK: u32 = 1_000_000_000;
+
+        let mut chunks = Vec::new();
+        let mut magnitude = self.magnitude.clone();
+
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = div_rem_small(&magnitude, CHUNK);
+            chunks.push(remainder);
+            magnitude = quotient;
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+
+        match chunks.split_last() {
+            None => write!(f, "0"),
+            Some((most_significant, rest)) => {
+                write!(f, "{}", most_significant)?;
+                rest.iter()
+                    .rev()
+                    .try_for_each(|chunk| write!(f, "{:09}", chunk))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> BigInt {
+        s.parse().unwrap()
+    }
+
+    fn from_i128(n: i128) -> BigInt {
+        big(&n.to_string())
+    }
+
+    const VALUES: &[i128] = &[
+        0,
+        1,
+        -1,
+        7,
+        -13,
+        u32::MAX as i128,
+        1 << 32,
+        -(1 << 32) - 5,
+        123_456_789_012,
+        -987_654_321_098_765,
+        i64::MAX as i128,
+        i64::MIN as i128,
+        (1 << 62) + 12_345,
+    ];
+
+    #[test]
+    fn parses_and_displays() {
+        for s in &[
+            "0",
+            "-7",
+            "1000000000",
+            "123456789012345678901234567890",
+            "-4294967296",
+        ] {
+            assert_eq!(big(s).to_string(), *s);
+        }
+        assert_eq!(big("-0").to_string(), "0");
+        assert_eq!(big("000000000012").to_string(), "12");
+        assert_eq!(
+            big("1000000000000000000").to_string(),
+            "1000000000000000000"
+        );
+
+        for s in &["", "-", "+1", "1a", "--1", " 1"] {
+            assert_eq!(s.parse::<BigInt>(), Err(()), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn arithmetic_agrees_with_i128() {
+        for &a in VALUES {
+            for &b in VALUES {
+                let (x, y) = (from_i128(a), from_i128(b));
+                assert_eq!(&x + &y, from_i128(a + b), "{} + {}", a, b);
+                assert_eq!(&x - &y, from_i128(a - b), "{} - {}", a, b);
+                assert_eq!(&x * &y, from_i128(a * b), "{} * {}", a, b);
+
+                let expected = a.checked_div(b).map(|q| (from_i128(q), from_i128(a % b)));
+                assert_eq!(x.div_rem(&y), expected, "{} / {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn multi_limb_division() {
+        let n = &big("2").pow(200) + &big("12345");
+        let d = &big("2").pow(100) + &big("1");
+        let (q, r) = n.div_rem(&d).unwrap();
+
+        assert_eq!(&(&q * &d) + &r, n);
+        assert_eq!(cmp_magnitude(&r.magnitude, &d.magnitude), Ordering::Less);
+        assert_eq!(q.to_string(), "1267650600228229401496703205375");
+        assert_eq!(r.to_string(), "12346");
+
+        let (q, r) = (-n.clone()).div_rem(&d).unwrap();
+        assert_eq!(q.to_string(), "-1267650600228229401496703205375");
+        assert_eq!(r.to_string(), "-12346");
+    }
+
+    #[test]
+    fn powers() {
+        assert_eq!(
+            big("2").pow(100).to_string(),
+            "1267650600228229401496703205376"
+        );
+        assert_eq!(big("-3").pow(5), big("-243"));
+        assert_eq!(big("-3").pow(4), big("81"));
+        assert_eq!(big("0").pow(0), big("1"));
+        assert_eq!(big("0").pow(3), big("0"));
+        assert_eq!(big("10").pow(30), big("1000000000000000000000000000000"));
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(from_i128(i64::MIN as i128).to_i64(), Some(i64::MIN));
+        assert_eq!(from_i128(i64::MAX as i128).to_i64(), Some(i64::MAX));
+        assert_eq!(from_i128(i64::MAX as i128 + 1).to_i64(), None);
+        assert_eq!(from_i128(i64::MIN as i128 - 1).to_i64(), None);
+        assert_eq!(big("4294967295").to_u32(), Some(u32::MAX));
+        assert_eq!(big("4294967296").to_u32(), None);
+        assert_eq!(big("-1").to_u32(), None);
+
+        assert_eq!(big("0").bits(), 0);
+        assert_eq!(big("-4294967296").bits(), 33);
+        assert!(big("0").is_even() && big("-4294967296").is_even());
+        assert!(!big("-4294967297").is_even());
+    }
+}
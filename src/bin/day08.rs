@@ -1,90 +1,197 @@
 #![feature(str_split_once)]
 
-use std::{collections::HashSet, str::FromStr};
+use advent_of_code_2020::console::{
+    asm::assemble,
+    cfg::ControlFlowGraph,
+    debugger::Debugger,
+    repair::{self, RepairOptions},
+    trace::Trace,
+    Halt, Instruction, Machine,
+};
 
-#[derive(Clone)]
-enum Instruction {
-    Acc(i32),
-    Jmp(i32),
-    Nop(i32),
+fn execute(program: &[Instruction]) -> Result<i32, i32> {
+    let mut machine = Machine::new(program.to_vec());
+
+    match machine.run() {
+        Halt::Terminated => Ok(machine.accumulator()),
+        _ => Err(machine.accumulator()),
+    }
 }
 
-impl FromStr for Instruction {
-    type Err = ();
+#[derive(Debug, PartialEq)]
+struct Patch {
+    corrupted_instruction: usize,
+    correct_output: i32,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (op, val) = s.split_once(" ").ok_or(())?;
-        let val = val.parse().map_err(|_| ())?;
+fn patch(program: &[Instruction], i: usize) -> Option<Patch> {
+    let mut patched_program = program.to_vec();
+    patched_program[i] = program[i].flipped()?;
 
-        match op {
-            "acc" => Ok(Self::Acc(val)),
-            "jmp" => Ok(Self::Jmp(val)),
-            "nop" => Ok(Self::Nop(val)),
-            _ => Err(()),
-        }
-    }
+    execute(&patched_program).ok().map(|output| Patch {
+        corrupted_instruction: i,
+        correct_output: output,
+    })
 }
 
-fn execute(program: &[Instruction]) -> Result<i32, i32> {
-    let mut accumulator = 0;
-    let mut program_counter = 0;
-    let mut executed_lines = HashSet::new();
+fn fix_program(program: &[Instruction]) -> Option<Patch> {
+    let len = program.len() as i32;
 
-    while program_counter < program.len() as i32 {
-        if !executed_lines.insert(program_counter) {
-            return Err(accumulator);
+    let mut predecessors = vec![Vec::new(); program.len() + 1];
+    for (i, instruction) in program.iter().enumerate() {
+        let successor = instruction.successor(i as i32);
+        if (0..=len).contains(&successor) {
+            predecessors[successor as usize].push(i);
         }
+    }
 
-        match program[program_counter as usize] {
-            Instruction::Acc(val) => accumulator += val,
-            Instruction::Jmp(val) => {
-                program_counter += val;
-                continue;
-            }
-            Instruction::Nop(_) => {}
+    let mut reaches_end = vec![false; program.len() + 1];
+    let mut unvisited = vec![program.len()];
+    while let Some(i) = unvisited.pop() {
+        if !std::mem::replace(&mut reaches_end[i], true) {
+            unvisited.extend(&predecessors[i]);
         }
-
-        program_counter += 1;
     }
 
-    Ok(accumulator)
+    let mut executed = Vec::new();
+    Machine::new(program.to_vec()).run_with(|step| executed.push(step.index));
+
+    executed.into_iter().find_map(|i| {
+        let successor = program[i].flipped()?.successor(i as i32);
+        if (0..=len).contains(&successor) && reaches_end[successor as usize] {
+            patch(program, i)
+        } else {
+            None
+        }
+    })
 }
 
-struct Patch {
-    _corrupted_instruction: usize,
-    correct_output: i32,
+fn analyze(cfg: &ControlFlowGraph) {
+    let join = |indices: &[usize]| {
+        indices
+            .iter()
+            .map(usize::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    println!("unreachable: {}", join(&cfg.unreachable()));
+    println!("out of bounds jumps: {}", join(&cfg.out_of_bounds_jumps()));
+
+    for cycle in cfg.loops() {
+        println!("loop of {} instructions: {}", cycle.len(), join(&cycle));
+    }
 }
 
-fn fix_program(program: &[Instruction]) -> Option<Patch> {
-    for i in 0..program.len() {
-        if let Instruction::Acc(_) = program[i] {
-            continue;
+fn repair_options(flags: &[String]) -> RepairOptions {
+    let mut options = RepairOptions::default();
+
+    for flag in flags {
+        let (name, value) = flag.split_once('=').unwrap_or((flag, ""));
+
+        match name {
+            "--max-edits" => options.max_edits = value.parse().unwrap(),
+            "--max-executions" => options.max_executions = value.parse().unwrap(),
+            "--no-flips" => options.flips = false,
+            "--deletions" => options.deletions = true,
+            "--operand-deltas" => {
+                options.operand_deltas = value.split(',').map(|d| d.parse().unwrap()).collect()
+            }
+            _ => panic!("unknown repair option: {}", flag),
         }
+    }
 
-        let mut patched_program = program.to_vec();
-        patched_program[i] = match patched_program[i] {
-            Instruction::Acc(_) => unreachable!(),
-            Instruction::Jmp(val) => Instruction::Nop(val),
-            Instruction::Nop(val) => Instruction::Jmp(val),
-        };
+    options
+}
+
+fn repair_report(program: &[Instruction], options: RepairOptions) {
+    let search = repair::search(program, &options);
 
-        if let Ok(output) = execute(&patched_program) {
-            return Some(Patch {
-                _corrupted_instruction: i,
-                correct_output: output,
-            });
+    for repair in &search.repairs {
+        let edits = repair
+            .edits
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        if edits.is_empty() {
+            println!("no edits needed => {}", repair.accumulator);
+        } else {
+            println!("{} => {}", edits.join(", "), repair.accumulator);
         }
     }
 
-    None
+    if search.repairs.is_empty() {
+        println!("no repair found");
+    }
+
+    if !search.complete {
+        println!(
+            "search stopped after {} executions, more repairs may exist",
+            search.executions
+        );
+    }
 }
 
 fn main() {
-    let program = include_str!("../../inputs/day08.txt")
-        .lines()
-        .filter_map(|line| line.parse().ok())
-        .collect::<Vec<Instruction>>();
+    let (flags, paths) = std::env::args()
+        .skip(1)
+        .partition::<Vec<_>, _>(|arg| arg.starts_with("--"));
+
+    let source = match paths.first() {
+        Some(path) => std::fs::read_to_string(path).unwrap(),
+        None => include_str!("../../inputs/day08.txt").to_owned(),
+    };
+
+    let program = match assemble(&source) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match flags.first().map(String::as_str) {
+        Some("--trace") => print!("{}", Trace::record(&mut Machine::new(program))),
+        Some("--analyze") => analyze(&ControlFlowGraph::new(&program)),
+        Some("--dot") => print!("{}", ControlFlowGraph::new(&program).to_dot()),
+        Some("--repair") => repair_report(&program, repair_options(&flags[1..])),
+        Some("--debug") => {
+            let stdin = std::io::stdin();
+            Debugger::new(Machine::new(program))
+                .run(stdin.lock(), std::io::stdout())
+                .unwrap();
+        }
+        Some(flag) => eprintln!("unknown flag: {}", flag),
+        None => match execute(&program) {
+            Ok(output) => println!("{}", output),
+            Err(output) => {
+                println!("{}", output);
+                let patch = fix_program(&program).unwrap();
+                println!("{}", patch.correct_output);
+                eprintln!("corrupted instruction: {}", patch.corrupted_instruction);
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    println!("{}", execute(&program).unwrap_err());
-    println!("{}", fix_program(&program).unwrap().correct_output);
+    fn fix_program_brute_force(program: &[Instruction]) -> Option<Patch> {
+        (0..program.len()).find_map(|i| patch(program, i))
+    }
+
+    #[test]
+    fn fix_program_matches_brute_force_on_puzzle_input() {
+        let program = assemble(include_str!("../../inputs/day08.txt")).unwrap();
+        let expected = Patch {
+            corrupted_instruction: 394,
+            correct_output: 1539,
+        };
+
+        assert_eq!(fix_program_brute_force(&program), Some(expected));
+        assert_eq!(fix_program(&program), fix_program_brute_force(&program));
+    }
 }
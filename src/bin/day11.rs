@@ -1,37 +1,18 @@
-use std::{
-    collections::{HashMap, HashSet},
-    convert::TryInto,
-    str::FromStr,
-};
+use std::{convert::TryInto, str::FromStr};
 
 type Position = (i32, i32);
 
-#[derive(Clone, Default)]
-struct Seat {
-    occupied: bool,
-    neighbors: HashSet<Position>,
-}
-
-impl Seat {
-    fn new(occupied: bool) -> Self {
-        Self {
-            occupied,
-            ..Self::default()
-        }
-    }
-}
+const USAGE: &str =
+    "usage: day11 <adjacency|line-of-sight[:range]> [crowding] [birth] [bounded|torus|walls]";
 
-impl PartialEq<Seat> for Seat {
-    fn eq(&self, other: &Seat) -> bool {
-        self.occupied == other.occupied
-    }
-}
-
-#[derive(Clone, PartialEq)]
+#[derive(Clone)]
 struct SeatsLayout {
     rows: i32,
     columns: i32,
-    seats: HashMap<Position, Seat>,
+    positions: Vec<Position>,
+    occupied: Vec<bool>,
+    neighbors: Vec<Vec<usize>>,
+    walls: Vec<usize>,
 }
 
 impl FromStr for SeatsLayout {
@@ -39,34 +20,123 @@ impl FromStr for SeatsLayout {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let rows = s.lines().count();
-        let columns = s.lines().next().ok_or(())?.chars().count();
+        let columns = s.lines().map(|line| line.chars().count()).max().ok_or(())?;
 
-        let seats = s
+        let (positions, occupied) = s
             .lines()
             .enumerate()
             .flat_map(|(x, line)| line.chars().enumerate().map(move |(y, c)| (x, y, c)))
             .filter_map(|(x, y, c)| {
                 let pos = (x.try_into().unwrap(), y.try_into().unwrap());
                 match c {
-                    'L' => Some((pos, Seat::new(false))),
-                    '#' => Some((pos, Seat::new(true))),
+                    'L' => Some((pos, false)),
+                    '#' => Some((pos, true)),
                     _ => None,
                 }
             })
-            .collect();
+            .unzip::<_, _, Vec<_>, Vec<_>>();
 
         Ok(Self {
             rows: rows.try_into().unwrap(),
             columns: columns.try_into().unwrap(),
-            seats,
+            neighbors: vec![Vec::new(); positions.len()],
+            walls: vec![0; positions.len()],
+            positions,
+            occupied,
         })
     }
 }
 
 #[derive(Clone, Copy)]
-enum Rule {
+enum Neighborhood {
     Adjacency,
-    FieldOfVision,
+    LineOfSight,
+    LimitedLineOfSight(usize),
+}
+
+impl FromStr for Neighborhood {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some("adjacency"), None) => Ok(Self::Adjacency),
+            (Some("line-of-sight"), None) => Ok(Self::LineOfSight),
+            (Some("line-of-sight"), Some(range)) => match range.parse() {
+                Ok(0) | Err(_) => Err(()),
+                Ok(range) => Ok(Self::LimitedLineOfSight(range)),
+            },
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum EdgeMode {
+    Bounded,
+    Toroidal,
+    Walls,
+}
+
+impl FromStr for EdgeMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bounded" => Ok(Self::Bounded),
+            "torus" => Ok(Self::Toroidal),
+            "walls" => Ok(Self::Walls),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Rule {
+    neighborhood: Neighborhood,
+    crowding_threshold: usize,
+    birth_threshold: usize,
+    edges: EdgeMode,
+}
+
+impl Rule {
+    const ADJACENCY: Self = Self {
+        neighborhood: Neighborhood::Adjacency,
+        crowding_threshold: 4,
+        birth_threshold: 0,
+        edges: EdgeMode::Bounded,
+    };
+
+    const FIELD_OF_VISION: Self = Self {
+        neighborhood: Neighborhood::LineOfSight,
+        crowding_threshold: 5,
+        birth_threshold: 0,
+        edges: EdgeMode::Bounded,
+    };
+
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, ()> {
+        let neighborhood = args.next().ok_or(())?.parse()?;
+        let defaults = match neighborhood {
+            Neighborhood::Adjacency => Self::ADJACENCY,
+            _ => Self::FIELD_OF_VISION,
+        };
+
+        let mut threshold = |default| {
+            args.next()
+                .map_or(Ok(default), |arg| arg.parse().map_err(|_| ()))
+        };
+
+        let crowding_threshold = threshold(defaults.crowding_threshold)?;
+        let birth_threshold = threshold(defaults.birth_threshold)?;
+        let edges = args.next().map_or(Ok(defaults.edges), |arg| arg.parse())?;
+
+        Ok(Self {
+            neighborhood,
+            crowding_threshold,
+            birth_threshold,
+            edges,
+        })
+    }
 }
 
 fn all_directions() -> impl Iterator<Item = Position> {
@@ -77,86 +147,126 @@ fn all_directions() -> impl Iterator<Item = Position> {
 
 impl SeatsLayout {
     fn occupancy(&self) -> usize {
-        self.seats.values().filter(|seat| seat.occupied).count()
+        self.occupied.iter().filter(|&&occupied| occupied).count()
     }
 
     fn _locate_neighboring_seats(&mut self, rule: Rule) {
-        let seats_positions = self.seats.keys().cloned().collect::<HashSet<_>>();
         let (max_x, max_y) = (self.rows, self.columns);
+        let cell = |(x, y): Position| (x * max_y + y) as usize;
 
-        for (&pos, seat) in &mut self.seats {
-            seat.neighbors = match rule {
-                Rule::Adjacency => {
-                    let (x, y) = pos;
-                    all_directions()
-                        .map(move |(dx, dy)| (x + dx, y + dy))
-                        .filter(|pos| seats_positions.contains(&pos))
-                        .collect()
-                }
+        let mut seat_at = vec![None; cell((max_x, 0))];
+        for (i, &pos) in self.positions.iter().enumerate() {
+            seat_at[cell(pos)] = Some(i);
+        }
+
+        let max_distance = match rule.neighborhood {
+            Neighborhood::Adjacency => 1,
+            Neighborhood::LineOfSight => usize::MAX,
+            Neighborhood::LimitedLineOfSight(range) => range,
+        };
+
+        for (i, &start) in self.positions.iter().enumerate() {
+            self.neighbors[i].clear();
+            self.walls[i] = 0;
 
-                Rule::FieldOfVision => all_directions()
-                    .filter_map(|(dx, dy)| {
-                        std::iter::successors(Some(pos), |(x, y)| {
-                            let new_x = x + dx;
-                            let new_y = y + dy;
-
-                            if (0..max_x).contains(&new_x) && (0..max_y).contains(&new_y) {
-                                Some((new_x, new_y))
-                            } else {
-                                None
-                            }
-                        })
-                        .skip(1)
-                        .find(|pos| seats_positions.contains(&pos))
-                    })
-                    .collect(),
-            };
+            for (dx, dy) in all_directions() {
+                let mut pos = start;
+
+                for _ in 0..max_distance {
+                    let (x, y) = (pos.0 + dx, pos.1 + dy);
+
+                    pos = match rule.edges {
+                        EdgeMode::Toroidal => (x.rem_euclid(max_x), y.rem_euclid(max_y)),
+                        _ if (0..max_x).contains(&x) && (0..max_y).contains(&y) => (x, y),
+                        EdgeMode::Walls => {
+                            self.walls[i] += 1;
+                            break;
+                        }
+                        EdgeMode::Bounded => break,
+                    };
+
+                    if pos == start {
+                        break;
+                    }
+
+                    if let Some(j) = seat_at[cell(pos)] {
+                        self.neighbors[i].push(j);
+                        break;
+                    }
+                }
+            }
         }
     }
 
-    fn _step(&self, rule: Rule) -> Self {
-        let new_layout = self
-            .seats
-            .iter()
-            .map(|(&pos, seat)| {
-                let mut new_seat = seat.clone();
-
-                let occupied_neighbors = seat
-                    .neighbors
-                    .iter()
-                    .filter(|pos| self.seats[pos].occupied)
-                    .count();
-
-                new_seat.occupied = match (rule, seat.occupied, occupied_neighbors) {
-                    (_, false, 0) => true,
-                    (Rule::FieldOfVision, true, n) if n >= 5 => false,
-                    (Rule::Adjacency, true, n) if n >= 4 => false,
-                    _ => seat.occupied,
+    fn final_occupancy(mut self, rule: Rule) -> Result<usize, usize> {
+        self._locate_neighboring_seats(rule);
+
+        let seats_count = self.positions.len();
+        let mut next = self.occupied.clone();
+        let mut pending = (0..seats_count).collect::<Vec<_>>();
+        let mut is_pending = vec![true; seats_count];
+        let mut saved = self.occupied.clone();
+        let mut saved_round = 0;
+        let mut differences = 0;
+
+        for round in 0.. {
+            if pending.is_empty() {
+                break;
+            }
+
+            if round > saved_round && differences == 0 {
+                return Err(round - saved_round);
+            }
+
+            if round.is_power_of_two() {
+                saved.copy_from_slice(&self.occupied);
+                saved_round = round;
+                differences = 0;
+            }
+
+            let mut changed = Vec::new();
+
+            for i in pending.drain(..) {
+                is_pending[i] = false;
+
+                let occupied_neighbors = self.walls[i]
+                    + self.neighbors[i]
+                        .iter()
+                        .filter(|&&j| self.occupied[j])
+                        .count();
+
+                next[i] = match (self.occupied[i], occupied_neighbors) {
+                    (false, n) if n <= rule.birth_threshold => true,
+                    (true, n) if n >= rule.crowding_threshold => false,
+                    _ => self.occupied[i],
                 };
 
-                (pos, new_seat)
-            })
-            .collect();
+                if next[i] != self.occupied[i] {
+                    changed.push(i);
+                }
+            }
 
-        Self {
-            rows: self.rows,
-            columns: self.columns,
-            seats: new_layout,
-        }
-    }
+            std::mem::swap(&mut self.occupied, &mut next);
 
-    fn final_occupancy(mut self, rule: Rule) -> usize {
-        fn fix<T: PartialEq>(first: T, f: impl Fn(&T) -> T) -> T {
-            std::iter::successors(Some(first), |x| {
-                let new = f(x);
-                (new != *x).then(|| new)
-            })
-            .last()
-            .unwrap()
+            for i in changed {
+                next[i] = self.occupied[i];
+
+                if self.occupied[i] == saved[i] {
+                    differences -= 1;
+                } else {
+                    differences += 1;
+                }
+
+                for &j in self.neighbors[i].iter().chain(Some(&i)) {
+                    if !is_pending[j] {
+                        is_pending[j] = true;
+                        pending.push(j);
+                    }
+                }
+            }
         }
 
-        self._locate_neighboring_seats(rule);
-        fix(self, |layout| layout._step(rule)).occupancy()
+        Ok(self.occupancy())
     }
 }
 
@@ -165,6 +275,83 @@ fn main() {
         .parse::<SeatsLayout>()
         .unwrap();
 
-    println!("{}", layout.clone().final_occupancy(Rule::Adjacency));
-    println!("{}", layout.final_occupancy(Rule::FieldOfVision));
+    let mut args = std::env::args().skip(1).peekable();
+
+    if args.peek().is_some() {
+        let rule = Rule::from_args(args).expect(USAGE);
+        match layout.final_occupancy(rule) {
+            Ok(occupancy) => println!("{}", occupancy),
+            Err(period) => println!("no stable layout (cycle of period {})", period),
+        }
+        return;
+    }
+
+    println!(
+        "{}",
+        layout.clone().final_occupancy(Rule::ADJACENCY).unwrap()
+    );
+    println!("{}", layout.final_occupancy(Rule::FIELD_OF_VISION).unwrap());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(s: &str) -> SeatsLayout {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn puzzle_answers() {
+        let input = layout(include_str!("../../inputs/day11.txt"));
+
+        assert_eq!(input.clone().final_occupancy(Rule::ADJACENCY), Ok(2289));
+        assert_eq!(input.final_occupancy(Rule::FIELD_OF_VISION), Ok(2059));
+    }
+
+    #[test]
+    fn ragged_rows() {
+        let ragged = layout("L.\nLLLL\nL");
+
+        assert_eq!(ragged.columns, 4);
+        assert_eq!(ragged.clone().final_occupancy(Rule::ADJACENCY), Ok(5));
+        assert_eq!(ragged.final_occupancy(Rule::FIELD_OF_VISION), Ok(6));
+    }
+
+    #[test]
+    fn edge_modes() {
+        let square = layout("LLL\nLLL\nLLL");
+        let with_edges = |edges| Rule {
+            edges,
+            ..Rule::ADJACENCY
+        };
+
+        assert_eq!(
+            square
+                .clone()
+                .final_occupancy(with_edges(EdgeMode::Bounded)),
+            Ok(4)
+        );
+        assert_eq!(
+            square.clone().final_occupancy(with_edges(EdgeMode::Walls)),
+            Ok(1)
+        );
+        assert_eq!(
+            square.final_occupancy(with_edges(EdgeMode::Toroidal)),
+            Err(2)
+        );
+    }
+
+    #[test]
+    fn cycle_report() {
+        let lonely = layout("L");
+        let rule = Rule {
+            birth_threshold: 8,
+            edges: EdgeMode::Walls,
+            ..Rule::ADJACENCY
+        };
+
+        assert_eq!(lonely.clone().final_occupancy(rule), Err(2));
+        assert_eq!(lonely.final_occupancy(Rule::ADJACENCY), Ok(1));
+    }
 }
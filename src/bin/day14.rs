@@ -1,53 +1,198 @@
 #![feature(str_split_once)]
 
-use std::{collections::HashMap, convert::TryInto, str::FromStr};
+use std::str::FromStr;
 
-#[derive(Clone, Debug)]
-struct Mask([char; 36]);
+fn word_mask(width: usize) -> u64 {
+    u64::MAX >> (64 - width)
+}
 
-impl Default for Mask {
-    fn default() -> Self {
-        Self(['X'; 36])
-    }
+#[derive(Clone, Copy, Debug)]
+struct Mask {
+    width: usize,
+    and: u64,
+    or: u64,
+    floating: u64,
 }
 
 impl Mask {
-    fn apply_to_value(&self, mut value: u64) -> u64 {
-        for (i, &mask_bit) in self.0.iter().rev().enumerate() {
-            match mask_bit {
-                '0' => value &= !(1 << i),
-                '1' => value |= 1 << i,
-                'X' => {}
-                _ => panic!(),
-            }
+    fn unset(width: usize) -> Self {
+        Self {
+            width,
+            and: !0,
+            or: 0,
+            floating: word_mask(width),
         }
-        value
     }
 
-    fn apply_to_address(&self, mut address: u64) -> Vec<u64> {
-        let mut floating_bits = Vec::new();
+    fn apply_to_value(&self, value: u64) -> u64 {
+        (value & self.and) | self.or
+    }
 
-        for (i, &mask_bit) in self.0.iter().rev().enumerate() {
-            match mask_bit {
-                '0' => {}
-                '1' => address |= 1 << i,
-                'X' => floating_bits.push(i),
-                _ => panic!(),
-            }
+    fn apply_to_address(&self, address: u64) -> AddressPattern {
+        AddressPattern {
+            fixed: (address | self.or) & !self.floating,
+            floating: self.floating,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct AddressPattern {
+    fixed: u64,
+    floating: u64,
+}
+
+impl AddressPattern {
+    fn exact(address: u64) -> Self {
+        Self {
+            fixed: address,
+            floating: 0,
+        }
+    }
+
+    fn to_binary(self, width: usize) -> String {
+        (0..width)
+            .rev()
+            .map(|i| match (self.floating >> i & 1, self.fixed >> i & 1) {
+                (1, _) => 'X',
+                (_, 1) => '1',
+                _ => '0',
+            })
+            .collect()
+    }
+
+    fn base_to_hex(self, width: usize) -> String {
+        format!("{:01$x}", self.fixed, width.div_ceil(4))
+    }
+
+    fn len(&self) -> u128 {
+        1 << self.floating.count_ones()
+    }
+
+    fn intersects(&self, other: &Self) -> bool {
+        let fixed_in_both = !self.floating & !other.floating;
+        (self.fixed ^ other.fixed) & fixed_in_both == 0
+    }
+
+    fn subtract(&self, other: &Self) -> Vec<Self> {
+        if !self.intersects(other) {
+            return vec![*self];
+        }
+
+        let mut pieces = Vec::new();
+        let mut rest = *self;
+        let mut split_bits = self.floating & !other.floating;
+
+        while split_bits != 0 {
+            let bit = split_bits & split_bits.wrapping_neg();
+            split_bits &= !bit;
+
+            rest.floating &= !bit;
+            pieces.push(Self {
+                fixed: rest.fixed | (!other.fixed & bit),
+                floating: rest.floating,
+            });
+            rest.fixed |= other.fixed & bit;
         }
 
-        let mut addresses = vec![address];
+        pieces
+    }
+}
+
+struct Cell {
+    pattern: AddressPattern,
+    value: u64,
+    line: usize,
+}
 
-        for i in floating_bits {
-            let mut new_addresses = Vec::new();
-            for address in &addresses {
-                new_addresses.push(address & !(1 << i));
-                new_addresses.push(address | (1 << i));
+struct FloatingMemory {
+    width: usize,
+    writes: Vec<Cell>,
+}
+
+impl FloatingMemory {
+    fn new(width: usize) -> Self {
+        Self {
+            width,
+            writes: Vec::new(),
+        }
+    }
+
+    fn write(&mut self, pattern: AddressPattern, value: u64, line: usize) {
+        self.writes.push(Cell {
+            pattern,
+            value,
+            line,
+        });
+    }
+
+    fn resolve(&self) -> Vec<Cell> {
+        let mut cells = Vec::<Cell>::new();
+
+        for (i, write) in self.writes.iter().enumerate() {
+            let mut visible = vec![write.pattern];
+
+            for later in &self.writes[i + 1..] {
+                visible = visible
+                    .iter()
+                    .flat_map(|piece| piece.subtract(&later.pattern))
+                    .collect();
             }
-            addresses = new_addresses;
+
+            cells.extend(visible.into_iter().map(|pattern| Cell {
+                pattern,
+                value: write.value,
+                line: write.line,
+            }));
+        }
+
+        cells.sort_by_key(|cell| cell.pattern.fixed);
+        cells
+    }
+
+    fn sum(&self) -> u128 {
+        self.resolve()
+            .iter()
+            .map(|cell| cell.pattern.len() * u128::from(cell.value))
+            .sum()
+    }
+
+    fn dump(&self) {
+        let hex_width = self.width.div_ceil(4) + 2;
+        println!(
+            "{:<hex$}  {:<bin$}  {:>20}  {:>6}",
+            "base",
+            "pattern",
+            "value",
+            "line",
+            hex = hex_width,
+            bin = self.width
+        );
+
+        for cell in self.resolve() {
+            println!(
+                "0x{:<hex$}  {}  {:>20}  {:>6}",
+                cell.pattern.base_to_hex(self.width),
+                cell.pattern.to_binary(self.width),
+                cell.value,
+                cell.line,
+                hex = hex_width - 2
+            );
         }
+    }
 
-        addresses
+    fn dump_csv(&self) {
+        println!("base_hex,pattern,value,line");
+
+        for cell in self.resolve() {
+            println!(
+                "0x{},{},{},{}",
+                cell.pattern.base_to_hex(self.width),
+                cell.pattern.to_binary(self.width),
+                cell.value,
+                cell.line
+            );
+        }
     }
 }
 
@@ -55,11 +200,27 @@ impl FromStr for Mask {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.chars()
-            .collect::<Vec<_>>()
-            .try_into()
-            .map(Self)
-            .map_err(|_| ())
+        if !(1..=64).contains(&s.len()) {
+            return Err(());
+        }
+
+        let mut mask = Self {
+            width: s.len(),
+            and: !0,
+            or: 0,
+            floating: 0,
+        };
+
+        for (i, mask_bit) in s.bytes().rev().enumerate() {
+            match mask_bit {
+                b'0' => mask.and &= !(1 << i),
+                b'1' => mask.or |= 1 << i,
+                b'X' => mask.floating |= 1 << i,
+                _ => return Err(()),
+            }
+        }
+
+        Ok(mask)
     }
 }
 
@@ -89,17 +250,66 @@ impl FromStr for Instruction {
     }
 }
 
-type Memory = HashMap<u64, u64>;
+impl Instruction {
+    fn fits(&self, width: usize) -> bool {
+        match self {
+            Self::UpdateMask(mask) => mask.width == width,
+            Self::Write { address, value } => (address | value) & !word_mask(width) == 0,
+        }
+    }
+}
+
+fn parse_program(input: &str, width: usize) -> Result<Vec<Instruction>, ()> {
+    if !(1..=64).contains(&width) {
+        return Err(());
+    }
+
+    input
+        .lines()
+        .map(|line| line.parse().ok().filter(|i: &Instruction| i.fits(width)))
+        .collect::<Option<_>>()
+        .ok_or(())
+}
+
+#[derive(Clone, Copy)]
+enum DecoderVersion {
+    ValueMasking,
+    AddressMasking,
+    Combined,
+}
+
+impl FromStr for DecoderVersion {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "value" => Ok(Self::ValueMasking),
+            "address" => Ok(Self::AddressMasking),
+            "combined" => Ok(Self::Combined),
+            _ => Err(()),
+        }
+    }
+}
 
-fn run(program: &[Instruction], write_rule: impl Fn(&mut Memory, &Mask, u64, u64)) -> Memory {
-    let mut memory = HashMap::new();
-    let mut mask = Mask::default();
+fn run(program: &[Instruction], decoder: DecoderVersion, width: usize) -> FloatingMemory {
+    let mut memory = FloatingMemory::new(width);
+    let mut mask = Mask::unset(width);
 
-    for instruction in program {
-        match instruction {
-            Instruction::UpdateMask(new_mask) => mask = new_mask.clone(),
+    for (i, instruction) in program.iter().enumerate() {
+        match *instruction {
+            Instruction::UpdateMask(new_mask) => mask = new_mask,
             Instruction::Write { address, value } => {
-                write_rule(&mut memory, &mask, *address, *value)
+                let (pattern, value) = match decoder {
+                    DecoderVersion::ValueMasking => {
+                        (AddressPattern::exact(address), mask.apply_to_value(value))
+                    }
+                    DecoderVersion::AddressMasking => (mask.apply_to_address(address), value),
+                    DecoderVersion::Combined => {
+                        (mask.apply_to_address(address), mask.apply_to_value(value))
+                    }
+                };
+
+                memory.write(pattern, value, i + 1);
             }
         }
     }
@@ -108,27 +318,111 @@ fn run(program: &[Instruction], write_rule: impl Fn(&mut Memory, &Mask, u64, u64
 }
 
 fn main() {
-    let program = include_str!("../../inputs/day14.txt")
-        .lines()
-        .map(str::parse)
-        .collect::<Result<Vec<Instruction>, _>>()
-        .unwrap();
+    let input = include_str!("../../inputs/day14.txt");
+    let (flags, mut args) = std::env::args()
+        .skip(1)
+        .partition::<Vec<_>, _>(|arg| arg.starts_with("--"));
+
+    if flags.is_empty() && args.is_empty() {
+        let program = parse_program(input, 36).unwrap();
+
+        println!("{}", run(&program, DecoderVersion::ValueMasking, 36).sum());
+        println!(
+            "{}",
+            run(&program, DecoderVersion::AddressMasking, 36).sum()
+        );
+        return;
+    }
+
+    let usage = "usage: day14 [--dump|--csv] [value|address|combined] [width] [program]";
+    if args.len() > 3 {
+        panic!("{}", usage);
+    }
+    args.resize(3, String::new());
+
+    let decoder = match args[0].as_str() {
+        "" => DecoderVersion::AddressMasking,
+        decoder => decoder.parse().expect(usage),
+    };
+    let width = match args[1].as_str() {
+        "" => 36,
+        width => width.parse().expect(usage),
+    };
+    let input = match args[2].as_str() {
+        "" => input.to_owned(),
+        path => std::fs::read_to_string(path).unwrap(),
+    };
+
+    let program = parse_program(&input, width).expect("invalid program for this word width");
+    let memory = run(&program, decoder, width);
+
+    match flags.first().map(String::as_str) {
+        None => println!("{}", memory.sum()),
+        Some("--dump") => memory.dump(),
+        Some("--csv") => memory.dump_csv(),
+        Some(_) => panic!("{}", usage),
+    }
+}
 
-    let v1_output = run(&program, |memory, mask, address, value| {
-        memory.insert(address, mask.apply_to_value(value));
-    })
-    .values()
-    .sum::<u64>();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
 
-    println!("{}", v1_output);
+    fn brute_force_sum(memory: &FloatingMemory) -> u128 {
+        let mut cells = HashMap::new();
 
-    let v2_output = run(&program, |memory, mask, address, value| {
-        for address in mask.apply_to_address(address) {
-            memory.insert(address, value);
+        for write in &memory.writes {
+            let AddressPattern { fixed, floating } = write.pattern;
+            let mut subset = 0u64;
+            loop {
+                cells.insert(fixed | subset, write.value);
+                subset = subset.wrapping_sub(floating) & floating;
+                if subset == 0 {
+                    break;
+                }
+            }
+        }
+
+        cells.values().map(|&value| u128::from(value)).sum()
+    }
+
+    #[test]
+    fn resolve_matches_brute_force() {
+        let mut seed = 14u64;
+        let mut random = move || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            seed >> 33
+        };
+
+        for _ in 0..20 {
+            let mut memory = FloatingMemory::new(12);
+            for line in 0..40 {
+                let floating = random() & random() & word_mask(12);
+                let pattern = AddressPattern {
+                    fixed: random() & word_mask(12) & !floating,
+                    floating,
+                };
+                memory.write(pattern, random() % 1000, line);
+            }
+
+            assert_eq!(memory.sum(), brute_force_sum(&memory));
         }
-    })
-    .values()
-    .sum::<u64>();
+    }
+
+    #[test]
+    fn puzzle_answers() {
+        let program = parse_program(include_str!("../../inputs/day14.txt"), 36).unwrap();
 
-    println!("{}", v2_output);
+        assert_eq!(
+            run(&program, DecoderVersion::ValueMasking, 36).sum(),
+            11501064782628
+        );
+        assert_eq!(
+            run(&program, DecoderVersion::AddressMasking, 36).sum(),
+            5142195937660
+        );
+    }
 }
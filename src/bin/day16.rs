@@ -1,15 +1,17 @@
-#![feature(str_split_once, hash_drain_filter)]
+#![feature(str_split_once)]
+
+use advent_of_code_2020::interval_set::IntervalSet;
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap},
+    fmt,
     num::ParseIntError,
-    ops::RangeInclusive,
     str::FromStr,
 };
 
 struct TicketRule {
     field_label: String,
-    valid_ranges: Vec<RangeInclusive<u32>>,
+    valid_ranges: IntervalSet,
 }
 
 impl FromStr for TicketRule {
@@ -18,24 +20,16 @@ impl FromStr for TicketRule {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (field_label, valid_ranges) = s.split_once(": ").ok_or(())?;
 
-        let valid_ranges = valid_ranges
-            .split(" or ")
-            .map(|r| {
-                let (min, max) = r.split_once("-").unwrap();
-                min.parse().unwrap()..=max.parse().unwrap()
-            })
-            .collect();
-
         Ok(Self {
             field_label: field_label.to_owned(),
-            valid_ranges,
+            valid_ranges: valid_ranges.parse()?,
         })
     }
 }
 
 impl TicketRule {
     fn is_in_range(&self, value: u32) -> bool {
-        self.valid_ranges.iter().any(|r| r.contains(&value))
+        self.valid_ranges.contains(value)
     }
 }
 
@@ -55,64 +49,299 @@ impl FromStr for Ticket {
     }
 }
 
-fn error_rate(tickets: &[Ticket], rules: &[TicketRule]) -> u32 {
+fn error_rate(tickets: &[Ticket], any_rule: &IntervalSet) -> u32 {
     let invalid_values = tickets.iter().flat_map(|ticket| {
         ticket
             .values
             .iter()
-            .filter(|&&value| !rules.iter().any(|rule| rule.is_in_range(value)))
+            .filter(|&&value| !any_rule.contains(value))
     });
 
     invalid_values.sum()
 }
 
-fn solve(tickets: &[Ticket], rules: &[TicketRule]) -> HashMap<usize, String> {
-    let mut fields = (0..rules.len())
-        .map(|i| {
-            let all_values_match = |rule: &TicketRule| {
-                tickets
+struct TicketReport<'a> {
+    line: usize,
+    values: Vec<(u32, Vec<&'a str>)>,
+}
+
+impl TicketReport<'_> {
+    fn invalid_values(&self) -> impl Iterator<Item = u32> + '_ {
+        self.values
+            .iter()
+            .filter(|(_, labels)| labels.is_empty())
+            .map(|&(value, _)| value)
+    }
+
+    fn is_valid(&self) -> bool {
+        self.invalid_values().next().is_none()
+    }
+}
+
+struct ValidationReport<'a> {
+    tickets: Vec<TicketReport<'a>>,
+}
+
+impl<'a> ValidationReport<'a> {
+    fn new(tickets: &[Ticket], first_line: usize, rules: &'a [TicketRule]) -> Self {
+        let tickets = tickets
+            .iter()
+            .enumerate()
+            .map(|(i, ticket)| TicketReport {
+                line: first_line + i,
+                values: ticket
+                    .values
                     .iter()
-                    .all(|ticket| rule.is_in_range(ticket.values[i]))
+                    .map(|&value| {
+                        let labels = rules
+                            .iter()
+                            .filter(|rule| rule.is_in_range(value))
+                            .map(|rule| rule.field_label.as_str())
+                            .collect();
+                        (value, labels)
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Self { tickets }
+    }
+
+    fn error_rate(&self) -> u32 {
+        self.tickets
+            .iter()
+            .flat_map(TicketReport::invalid_values)
+            .sum()
+    }
+
+    fn summary(&self) -> String {
+        let invalid_tickets = self.tickets.iter().filter(|t| !t.is_valid()).count();
+        let invalid_values = self
+            .tickets
+            .iter()
+            .flat_map(TicketReport::invalid_values)
+            .count();
+
+        format!(
+            "{} tickets, {} valid, {} invalid\n{} invalid values, error rate {}",
+            self.tickets.len(),
+            self.tickets.len() - invalid_tickets,
+            invalid_tickets,
+            invalid_values,
+            self.error_rate()
+        )
+    }
+
+    fn print(&self) {
+        for ticket in &self.tickets {
+            let status = if ticket.is_valid() {
+                "valid"
+            } else {
+                "invalid"
             };
+            println!("line {}: {}", ticket.line, status);
 
-            let possible_labels = rules
-                .iter()
-                .filter(|rule| all_values_match(rule))
-                .map(|rule| rule.field_label.clone())
-                .collect::<HashSet<_>>();
+            for (field, (value, labels)) in ticket.values.iter().enumerate() {
+                if labels.is_empty() {
+                    println!("  {:>2}  {:>4}  fits no rule", field, value);
+                } else {
+                    println!("  {:>2}  {:>4}  {}", field, value, labels.join(", "));
+                }
+            }
+        }
 
-            (i, possible_labels)
-        })
-        .collect::<HashMap<_, _>>();
+        println!("\n{}", self.summary());
+    }
+
+    fn print_csv(&self) {
+        println!("line,field,value,valid,rules");
 
-    let mut fixed = HashMap::new();
+        for ticket in &self.tickets {
+            for (field, (value, labels)) in ticket.values.iter().enumerate() {
+                println!(
+                    "{},{},{},{},\"{}\"",
+                    ticket.line,
+                    field,
+                    value,
+                    !labels.is_empty(),
+                    labels.join(";")
+                );
+            }
+        }
 
-    loop {
-        let new_fixed = fields
-            .drain_filter(|_i, possible_labels| possible_labels.len() == 1)
-            .map(|(i, mut possible_labels)| (i, possible_labels.drain().next().unwrap()))
-            .collect::<HashMap<_, _>>();
+        eprintln!("{}", self.summary());
+    }
+}
 
-        fixed.extend(new_fixed.clone());
+#[derive(Debug)]
+enum FieldMappingError {
+    Ambiguous {
+        mapping: HashMap<usize, String>,
+        alternatives: Vec<(usize, Vec<String>)>,
+    },
+    Impossible {
+        fields: Vec<usize>,
+        labels: Vec<String>,
+    },
+}
 
-        if new_fixed.is_empty() {
-            break;
+impl fmt::Display for FieldMappingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ambiguous {
+                mapping,
+                alternatives,
+            } => {
+                write!(f, "ambiguous field mapping:")?;
+                for (field, labels) in alternatives {
+                    write!(
+                        f,
+                        "\n  field {} is `{}` but could also be `{}`",
+                        field,
+                        mapping[field],
+                        labels.join("`, `")
+                    )?;
+                }
+                Ok(())
+            }
+            Self::Impossible { fields, labels } => write!(
+                f,
+                "impossible field mapping: fields {:?} only fit the {} labels `{}`",
+                fields,
+                labels.len(),
+                labels.join("`, `")
+            ),
         }
+    }
+}
+
+fn augment(
+    field: usize,
+    candidates: &[Vec<usize>],
+    owners: &mut [Option<usize>],
+    visited: &mut [bool],
+) -> bool {
+    for &rule in &candidates[field] {
+        if visited[rule] {
+            continue;
+        }
+        visited[rule] = true;
+
+        let free = match owners[rule] {
+            Some(owner) => augment(owner, candidates, owners, visited),
+            None => true,
+        };
+
+        if free {
+            owners[rule] = Some(field);
+            return true;
+        }
+    }
 
-        for possible_labels in fields.values_mut() {
-            for fixed in new_fixed.values() {
-                possible_labels.remove(fixed);
+    false
+}
+
+fn solve(
+    tickets: &[Ticket],
+    rules: &[TicketRule],
+) -> Result<HashMap<usize, String>, FieldMappingError> {
+    let candidates = (0..rules.len())
+        .map(|i| {
+            (0..rules.len())
+                .filter(|&rule| {
+                    tickets
+                        .iter()
+                        .all(|ticket| rules[rule].is_in_range(ticket.values[i]))
+                })
+                .collect()
+        })
+        .collect::<Vec<Vec<_>>>();
+
+    let mut owners = vec![None; rules.len()];
+    let unmatched = (0..rules.len())
+        .filter(|&field| {
+            !augment(
+                field,
+                &candidates,
+                &mut owners,
+                &mut vec![false; rules.len()],
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if !unmatched.is_empty() {
+        let mut fields = unmatched.iter().copied().collect::<BTreeSet<_>>();
+        let mut labels = BTreeSet::new();
+        let mut queue = unmatched;
+
+        while let Some(field) = queue.pop() {
+            for &rule in &candidates[field] {
+                if let (true, Some(owner)) = (labels.insert(rule), owners[rule]) {
+                    if fields.insert(owner) {
+                        queue.push(owner);
+                    }
+                }
             }
         }
+
+        return Err(FieldMappingError::Impossible {
+            fields: fields.into_iter().collect(),
+            labels: labels
+                .into_iter()
+                .map(|rule| rules[rule].field_label.clone())
+                .collect(),
+        });
     }
 
-    fixed
+    let mut assignment = vec![0; rules.len()];
+    for (rule, owner) in owners.iter().enumerate() {
+        assignment[owner.unwrap()] = rule;
+    }
+
+    let alternatives = (0..rules.len())
+        .map(|field| {
+            let others = candidates[field]
+                .iter()
+                .filter(|&&rule| {
+                    if rule == assignment[field] {
+                        return false;
+                    }
+
+                    let mut owners = owners.clone();
+                    let displaced = owners[rule].replace(field).unwrap();
+                    owners[assignment[field]] = None;
+
+                    let mut visited = vec![false; rules.len()];
+                    visited[rule] = true;
+                    augment(displaced, &candidates, &mut owners, &mut visited)
+                })
+                .map(|&rule| rules[rule].field_label.clone())
+                .collect::<Vec<_>>();
+
+            (field, others)
+        })
+        .filter(|(_, others)| !others.is_empty())
+        .collect::<Vec<_>>();
+
+    let mapping = assignment
+        .iter()
+        .enumerate()
+        .map(|(field, &rule)| (field, rules[rule].field_label.clone()))
+        .collect();
+
+    if alternatives.is_empty() {
+        Ok(mapping)
+    } else {
+        Err(FieldMappingError::Ambiguous {
+            mapping,
+            alternatives,
+        })
+    }
 }
 
 fn main() {
-    let (rules, tickets) = include_str!("../../inputs/day16.txt")
-        .split_once("\n\n")
-        .unwrap();
+    let input = include_str!("../../inputs/day16.txt");
+    let (rules, tickets) = input.split_once("\n\n").unwrap();
 
     let rules: Vec<TicketRule> = rules
         .lines()
@@ -120,7 +349,7 @@ fn main() {
         .collect::<Result<_, _>>()
         .unwrap();
 
-    let (my_ticket, nearby_tickets) = tickets.split_once("\n\n").unwrap();
+    let (my_ticket, nearby_tickets_input) = tickets.split_once("\n\n").unwrap();
 
     let my_ticket: Ticket = my_ticket
         .lines()
@@ -128,27 +357,45 @@ fn main() {
         .and_then(|l| l.parse().ok())
         .unwrap();
 
-    let nearby_tickets: Vec<Ticket> = nearby_tickets
+    let nearby_tickets: Vec<Ticket> = nearby_tickets_input
         .lines()
         .skip(1)
         .map(str::parse)
         .collect::<Result<_, _>>()
         .unwrap();
 
-    println!("{}", error_rate(&nearby_tickets, &rules));
+    let first_line = input[..input.len() - nearby_tickets_input.len()]
+        .lines()
+        .count()
+        + 2;
+    let report = || ValidationReport::new(&nearby_tickets, first_line, &rules);
+
+    match std::env::args().nth(1).as_deref() {
+        None => {}
+        Some("--report") => return report().print(),
+        Some("--csv") => return report().print_csv(),
+        Some(flag) => panic!("unknown flag: {}", flag),
+    }
+
+    let any_rule = rules.iter().fold(IntervalSet::new(), |set, rule| {
+        set.union(&rule.valid_ranges)
+    });
+
+    println!("{}", error_rate(&nearby_tickets, &any_rule));
 
     let maybe_valid = nearby_tickets
         .iter()
-        .filter(|ticket| {
-            ticket
-                .values
-                .iter()
-                .all(|&value| rules.iter().any(|rule| rule.is_in_range(value)))
-        })
+        .filter(|ticket| ticket.values.iter().all(|&value| any_rule.contains(value)))
         .cloned()
         .collect::<Vec<_>>();
 
-    let field_mapping = solve(&maybe_valid, &rules);
+    let field_mapping = solve(&maybe_valid, &rules).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        match e {
+            FieldMappingError::Ambiguous { mapping, .. } => mapping,
+            FieldMappingError::Impossible { .. } => std::process::exit(1),
+        }
+    });
 
     println!(
         "{}",
@@ -159,3 +406,101 @@ fn main() {
             .product::<u64>()
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(rules: &[&str]) -> Vec<TicketRule> {
+        rules.iter().map(|rule| rule.parse().unwrap()).collect()
+    }
+
+    fn tickets(tickets: &[&str]) -> Vec<Ticket> {
+        tickets
+            .iter()
+            .map(|ticket| ticket.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn report_agrees_with_rule_union() {
+        let rules = rules(&[
+            "class: 1-3 or 5-7",
+            "row: 6-11 or 33-44",
+            "seat: 13-40 or 45-50",
+        ]);
+        let tickets = tickets(&["7,3,47", "40,4,50", "55,2,20", "38,6,12"]);
+
+        let any_rule = rules.iter().fold(IntervalSet::new(), |set, rule| {
+            set.union(&rule.valid_ranges)
+        });
+        let report = ValidationReport::new(&tickets, 6, &rules);
+
+        assert_eq!(any_rule.to_string(), "1-3 or 5-11 or 13-50");
+        assert_eq!(error_rate(&tickets, &any_rule), 71);
+        assert_eq!(report.error_rate(), 71);
+        assert_eq!(
+            report.tickets.iter().map(|t| t.line).collect::<Vec<_>>(),
+            vec![6, 7, 8, 9]
+        );
+        assert_eq!(
+            report
+                .tickets
+                .iter()
+                .map(TicketReport::is_valid)
+                .collect::<Vec<_>>(),
+            vec![true, false, false, false]
+        );
+        assert_eq!(report.tickets[0].values[0], (7, vec!["class", "row"]));
+    }
+
+    #[test]
+    fn solves_unique_mapping() {
+        let rules = rules(&[
+            "class: 0-1 or 4-19",
+            "row: 0-5 or 8-19",
+            "seat: 0-13 or 16-19",
+        ]);
+        let tickets = tickets(&["3,9,18", "15,1,5", "5,14,9"]);
+
+        let mapping = solve(&tickets, &rules).unwrap();
+        assert_eq!(mapping[&0], "row");
+        assert_eq!(mapping[&1], "class");
+        assert_eq!(mapping[&2], "seat");
+    }
+
+    #[test]
+    fn reports_ambiguous_mapping() {
+        let rules = rules(&["a: 1-10", "b: 1-10"]);
+
+        match solve(&tickets(&["1,2"]), &rules) {
+            Err(FieldMappingError::Ambiguous {
+                mapping,
+                alternatives,
+            }) => {
+                assert_ne!(mapping[&0], mapping[&1]);
+                assert_eq!(
+                    alternatives,
+                    vec![
+                        (0, vec![mapping[&1].clone()]),
+                        (1, vec![mapping[&0].clone()])
+                    ]
+                );
+            }
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn reports_impossible_mapping() {
+        let rules = rules(&["a: 1-5", "b: 50-60", "c: 50-60"]);
+
+        match solve(&tickets(&["1,2,55"]), &rules) {
+            Err(FieldMappingError::Impossible { fields, labels }) => {
+                assert_eq!(fields, vec![0, 1]);
+                assert_eq!(labels, vec!["a"]);
+            }
+            result => panic!("{:?}", result),
+        }
+    }
+}
@@ -1,103 +1,1321 @@
+use advent_of_code_2020::bigint::BigInt;
+
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    convert::TryFrom,
+    fmt,
+    io::{self, BufRead, Write},
+    str::FromStr,
+};
+
+type Offset = usize;
+
 enum Expr {
-    BinOp(char, Box<Expr>, Box<Expr>),
-    Literal(u64),
+    BinOp(char, Offset, Box<Expr>, Box<Expr>),
+    Neg(Offset, Box<Expr>),
+    Literal(Offset, BigInt),
+    Var(Offset, String),
 }
 
-fn parse(input: &str, precedence: impl Fn(char) -> u8) -> Expr {
-    let mut output = Vec::new();
-    let mut ops_stack = Vec::new();
+impl Drop for Expr {
+    fn drop(&mut self) {
+        fn detach_children(expr: &mut Expr, detached: &mut Vec<Expr>) {
+            let leaf = || Expr::Literal(0, BigInt::default());
+            match expr {
+                Expr::BinOp(_, _, lhs, rhs) => {
+                    detached.push(std::mem::replace(lhs, leaf()));
+                    detached.push(std::mem::replace(rhs, leaf()));
+                }
+                Expr::Neg(_, operand) => detached.push(std::mem::replace(operand, leaf())),
+                Expr::Literal(..) | Expr::Var(..) => {}
+            }
+        }
 
-    let pop_two = |stack: &mut Vec<_>| {
-        let lhs = Box::new(stack.pop().unwrap());
-        let rhs = Box::new(stack.pop().unwrap());
-        (lhs, rhs)
-    };
+        let mut detached = Vec::new();
+        detach_children(self, &mut detached);
+        while let Some(mut expr) = detached.pop() {
+            detach_children(&mut expr, &mut detached);
+        }
+    }
+}
 
-    for symbol in input.chars() {
-        if symbol.is_whitespace() {
-            continue;
+impl Expr {
+    fn offset(&self) -> Offset {
+        match self {
+            Self::BinOp(_, offset, ..)
+            | Self::Neg(offset, _)
+            | Self::Literal(offset, _)
+            | Self::Var(offset, _) => *offset,
+        }
+    }
+}
+
+enum Statement {
+    Let(String, Expr),
+    Expr(Expr),
+}
+
+impl Statement {
+    fn expr(&self) -> &Expr {
+        match self {
+            Self::Let(_, expr) | Self::Expr(expr) => expr,
+        }
+    }
+}
+
+type Env = HashMap<String, BigInt>;
+
+#[derive(Debug, PartialEq)]
+enum ErrorKind {
+    UnexpectedCharacter(char),
+    UnbalancedParenthesis,
+    MissingOperand,
+    MissingOperator,
+    UnknownOperator(char),
+    DivisionByZero,
+    NegativeExponent,
+    ExponentTooLarge,
+    Overflow(String),
+    UnknownVariable(String),
+    ExpectedName,
+    ExpectedAssignment,
+}
+
+#[derive(Debug, PartialEq)]
+struct Error {
+    offset: Offset,
+    kind: ErrorKind,
+}
+
+impl Error {
+    fn new(offset: Offset, kind: ErrorKind) -> Self {
+        Self { offset, kind }
+    }
+
+    fn report(&self, line: &str) -> String {
+        format!("{}\n{}^\n{}", line, " ".repeat(self.offset), self)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character `{}`", c)?,
+            ErrorKind::UnbalancedParenthesis => write!(f, "unbalanced parenthesis")?,
+            ErrorKind::MissingOperand => write!(f, "missing operand")?,
+            ErrorKind::MissingOperator => write!(f, "missing operator")?,
+            ErrorKind::UnknownOperator(op) => write!(f, "unknown operator `{}`", op)?,
+            ErrorKind::DivisionByZero => write!(f, "division by zero")?,
+            ErrorKind::NegativeExponent => write!(f, "negative exponent")?,
+            ErrorKind::ExponentTooLarge => write!(f, "exponent too large")?,
+            ErrorKind::Overflow(expr) => write!(f, "`{}` overflows", expr)?,
+            ErrorKind::UnknownVariable(name) => write!(f, "unknown variable `{}`", name)?,
+            ErrorKind::ExpectedName => write!(f, "expected a variable name")?,
+            ErrorKind::ExpectedAssignment => write!(f, "expected `=`")?,
+        }
+        write!(f, " at offset {}", self.offset)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Associativity {
+    Left,
+    Right,
+}
+
+struct OperatorTable(HashMap<char, (u8, Associativity)>);
+
+impl FromStr for OperatorTable {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut operators = HashMap::new();
+
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            let (associativity, words) = words.split_last().ok_or(())?;
+            let (precedence, symbols) = words.split_last().ok_or(())?;
+
+            let associativity = match *associativity {
+                "left" => Associativity::Left,
+                "right" => Associativity::Right,
+                _ => return Err(()),
+            };
+            let precedence = precedence.parse().map_err(|_| ())?;
+
+            for symbol in symbols {
+                let mut chars = symbol.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(op), None) if "+-*/%^".contains(op) => {
+                        operators.insert(op, (precedence, associativity));
+                    }
+                    _ => return Err(()),
+                }
+            }
         }
 
+        Ok(Self(operators))
+    }
+}
+
+impl OperatorTable {
+    fn part_one() -> Self {
+        "+ - * / % ^ 1 left".parse().unwrap()
+    }
+
+    fn part_two() -> Self {
+        "^ 3 right\n+ - 2 left\n* / % 1 left".parse().unwrap()
+    }
+
+    fn standard() -> Self {
+        "^ 3 right\n* / % 2 left\n+ - 1 left".parse().unwrap()
+    }
+
+    fn get(&self, op: char) -> Option<(u8, Associativity)> {
+        self.0.get(&op).copied()
+    }
+
+    fn binding_power(&self, op: char) -> u16 {
+        match op {
+            NEGATION => {
+                let tightest = self
+                    .0
+                    .iter()
+                    .filter(|(&op, _)| op != '^')
+                    .map(|(_, &(precedence, _))| precedence)
+                    .max()
+                    .unwrap_or(0);
+                2 * u16::from(tightest) + 1
+            }
+            op => self
+                .get(op)
+                .map_or(0, |(precedence, _)| 2 * u16::from(precedence)),
+        }
+    }
+}
+
+enum Token {
+    Number(BigInt),
+    Name(String),
+    Symbol(char),
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Offset, Token)>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((offset, symbol)) = chars.next() {
         match symbol {
+            _ if symbol.is_whitespace() => {}
+
             '0'..='9' => {
-                let number = symbol.to_digit(10).unwrap() as u64;
-                output.push(Expr::Literal(number));
+                let mut end = offset + 1;
+                while let Some(&(i, '0'..='9')) = chars.peek() {
+                    end = i + 1;
+                    chars.next();
+                }
+
+                let number = input[offset..end].parse().unwrap();
+                tokens.push((offset, Token::Number(number)));
             }
 
-            '+' | '*' => {
-                while let Some(&op) = ops_stack.last() {
-                    if precedence(op) >= precedence(symbol) {
-                        let (lhs, rhs) = pop_two(&mut output);
-                        output.push(Expr::BinOp(op, lhs, rhs));
-                        ops_stack.pop();
-                    } else {
+            _ if symbol.is_ascii_alphabetic() || symbol == '_' => {
+                let mut end = offset + 1;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !c.is_ascii_alphanumeric() && c != '_' {
                         break;
                     }
+                    end = i + 1;
+                    chars.next();
                 }
-                ops_stack.push(symbol);
+
+                tokens.push((offset, Token::Name(input[offset..end].to_owned())));
+            }
+
+            '+' | '-' | '*' | '/' | '%' | '^' | '(' | ')' | '=' => {
+                tokens.push((offset, Token::Symbol(symbol)))
+            }
+
+            _ => return Err(Error::new(offset, ErrorKind::UnexpectedCharacter(symbol))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+const NEGATION: char = '~';
+
+fn parse_statement(line: &str, operators: &OperatorTable) -> Result<Statement, Error> {
+    let mut tokens = tokenize(line)?.into_iter().peekable();
+
+    match tokens.peek() {
+        Some((_, Token::Name(keyword))) if keyword == "let" => tokens.next(),
+        _ => return parse(tokens, line.len(), operators).map(Statement::Expr),
+    };
+
+    let name = match tokens.next() {
+        Some((_, Token::Name(name))) if name != "let" => name,
+        token => {
+            let offset = token.map_or(line.len(), |(offset, _)| offset);
+            return Err(Error::new(offset, ErrorKind::ExpectedName));
+        }
+    };
+
+    match tokens.next() {
+        Some((_, Token::Symbol('='))) => {}
+        token => {
+            let offset = token.map_or(line.len(), |(offset, _)| offset);
+            return Err(Error::new(offset, ErrorKind::ExpectedAssignment));
+        }
+    }
+
+    Ok(Statement::Let(name, parse(tokens, line.len(), operators)?))
+}
+
+fn parse(
+    tokens: impl Iterator<Item = (Offset, Token)>,
+    end: Offset,
+    operators: &OperatorTable,
+) -> Result<Expr, Error> {
+    let mut output = Vec::new();
+    let mut ops_stack = Vec::new();
+    let mut expects_operand = true;
+
+    let apply = |(op, offset), stack: &mut Vec<_>| {
+        let rhs = Box::new(stack.pop().unwrap());
+        let expr = match op {
+            NEGATION => Expr::Neg(offset, rhs),
+            op => Expr::BinOp(op, offset, Box::new(stack.pop().unwrap()), rhs),
+        };
+        stack.push(expr);
+    };
+
+    for (offset, token) in tokens {
+        match token {
+            Token::Number(_) | Token::Name(_) | Token::Symbol('(') if !expects_operand => {
+                return Err(Error::new(offset, ErrorKind::MissingOperator));
             }
 
-            '(' => ops_stack.push(symbol),
+            Token::Number(number) => {
+                output.push(Expr::Literal(offset, number));
+                expects_operand = false;
+            }
 
-            ')' => loop {
+            Token::Name(name) => {
+                output.push(Expr::Var(offset, name));
+                expects_operand = false;
+            }
+
+            Token::Symbol('(') => ops_stack.push(('(', offset)),
+
+            Token::Symbol('-') if expects_operand => ops_stack.push((NEGATION, offset)),
+
+            Token::Symbol(_) if expects_operand => {
+                return Err(Error::new(offset, ErrorKind::MissingOperand));
+            }
+
+            Token::Symbol(')') => loop {
                 match ops_stack.pop() {
-                    Some(op @ '+') | Some(op @ '*') => {
-                        let (lhs, rhs) = pop_two(&mut output);
-                        output.push(Expr::BinOp(op, lhs, rhs));
-                    }
-                    Some('(') => break,
-                    _ => panic!(),
+                    Some(('(', _)) => break,
+                    Some(op) => apply(op, &mut output),
+                    None => return Err(Error::new(offset, ErrorKind::UnbalancedParenthesis)),
                 }
             },
 
-            _ => panic!(),
+            Token::Symbol(symbol) => {
+                let associativity = match operators.get(symbol) {
+                    Some((_, associativity)) => associativity,
+                    None => return Err(Error::new(offset, ErrorKind::UnknownOperator(symbol))),
+                };
+
+                while let Some(&(op, op_offset)) = ops_stack.last() {
+                    let pops = match operators
+                        .binding_power(op)
+                        .cmp(&operators.binding_power(symbol))
+                    {
+                        Ordering::Greater => true,
+                        Ordering::Equal => associativity == Associativity::Left,
+                        Ordering::Less => false,
+                    };
+
+                    if op != '(' && pops {
+                        apply((op, op_offset), &mut output);
+                        ops_stack.pop();
+                    } else {
+                        break;
+                    }
+                }
+                ops_stack.push((symbol, offset));
+                expects_operand = true;
+            }
         }
     }
 
+    if expects_operand {
+        return Err(Error::new(end, ErrorKind::MissingOperand));
+    }
+
     while let Some(op) = ops_stack.pop() {
-        let (lhs, rhs) = pop_two(&mut output);
-        output.push(Expr::BinOp(op, lhs, rhs));
+        if let ('(', offset) = op {
+            return Err(Error::new(offset, ErrorKind::UnbalancedParenthesis));
+        }
+        apply(op, &mut output);
     }
 
-    assert_eq!(output.len(), 1);
-    output.pop().unwrap()
+    Ok(output.pop().unwrap())
+}
+
+const MAX_POWER_BITS: u64 = 1 << 20;
+
+trait Number: Sized + Clone + PartialEq + fmt::Debug {
+    fn zero() -> Self;
+    fn literal(n: &BigInt) -> Option<Self>;
+    fn to_big(&self) -> BigInt;
+    fn is_zero(&self) -> bool;
+    fn is_negative(&self) -> bool;
+    fn checked_neg(self) -> Option<Self>;
+    fn checked_op(op: char, lhs: Self, rhs: Self) -> Option<Self>;
+
+    fn exponent_too_large(_base: &Self, _exponent: &Self) -> bool {
+        false
+    }
+}
+
+impl Number for i64 {
+    fn zero() -> Self {
+        0
+    }
+
+    fn literal(n: &BigInt) -> Option<Self> {
+        n.to_i64()
+    }
+
+    fn to_big(&self) -> BigInt {
+        BigInt::from(*self)
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == 0
+    }
+
+    fn is_negative(&self) -> bool {
+        *self < 0
+    }
+
+    fn checked_neg(self) -> Option<Self> {
+        i64::checked_neg(self)
+    }
+
+    fn checked_op(op: char, lhs: Self, rhs: Self) -> Option<Self> {
+        match op {
+            '+' => lhs.checked_add(rhs),
+            '-' => lhs.checked_sub(rhs),
+            '*' => lhs.checked_mul(rhs),
+            '/' => lhs.checked_div(rhs),
+            '%' => lhs.checked_rem(rhs),
+            '^' => match lhs {
+                0 | 1 if rhs > 0 => Some(lhs),
+                -1 if rhs >= 0 => Some(if rhs % 2 == 0 { 1 } else { -1 }),
+                _ => u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_pow(rhs)),
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Number for BigInt {
+    fn zero() -> Self {
+        BigInt::from(0)
+    }
+
+    fn literal(n: &BigInt) -> Option<Self> {
+        Some(n.clone())
+    }
+
+    fn to_big(&self) -> BigInt {
+        self.clone()
+    }
+
+    fn is_zero(&self) -> bool {
+        BigInt::is_zero(self)
+    }
+
+    fn is_negative(&self) -> bool {
+        BigInt::is_negative(self)
+    }
+
+    fn checked_neg(self) -> Option<Self> {
+        Some(-self)
+    }
+
+    fn checked_op(op: char, lhs: Self, rhs: Self) -> Option<Self> {
+        match op {
+            '+' => Some(&lhs + &rhs),
+            '-' => Some(&lhs - &rhs),
+            '*' => Some(&lhs * &rhs),
+            '/' => lhs.div_rem(&rhs).map(|(quotient, _)| quotient),
+            '%' => lhs.div_rem(&rhs).map(|(_, remainder)| remainder),
+            '^' if rhs.is_negative() => None,
+            '^' => match lhs.to_i64() {
+                Some(0) | Some(1) if !rhs.is_zero() => Some(lhs),
+                Some(-1) if rhs.is_even() => Some(BigInt::from(1)),
+                Some(-1) => Some(lhs),
+                _ => rhs.to_u32().map(|rhs| lhs.pow(rhs)),
+            },
+            _ => None,
+        }
+    }
+
+    fn exponent_too_large(base: &Self, exponent: &Self) -> bool {
+        match (base.to_i64(), exponent.to_u32()) {
+            (Some(-1..=1), _) => false,
+            (_, Some(exponent)) => base.bits() * u64::from(exponent) > MAX_POWER_BITS,
+            (_, None) => true,
+        }
+    }
+}
+
+fn load<N: Number>(env: &Env, offset: Offset, name: &str) -> Result<N, Error> {
+    let value = env
+        .get(name)
+        .ok_or_else(|| Error::new(offset, ErrorKind::UnknownVariable(name.to_owned())))?;
+    N::literal(value).ok_or_else(|| Error::new(offset, ErrorKind::Overflow(name.to_owned())))
+}
+
+enum Bytecode<'a> {
+    Push(Offset, BigInt),
+    Load(Offset, String),
+    Add(&'a Expr),
+    Sub(&'a Expr),
+    Mul(&'a Expr),
+    Div(&'a Expr),
+    Rem(&'a Expr),
+    Pow(&'a Expr),
+    Neg(&'a Expr),
+}
+
+impl fmt::Display for Bytecode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Push(_, n) => write!(f, "push {}", n),
+            Self::Load(_, name) => write!(f, "load {}", name),
+            Self::Add(_) => write!(f, "add"),
+            Self::Sub(_) => write!(f, "sub"),
+            Self::Mul(_) => write!(f, "mul"),
+            Self::Div(_) => write!(f, "div"),
+            Self::Rem(_) => write!(f, "rem"),
+            Self::Pow(_) => write!(f, "pow"),
+            Self::Neg(_) => write!(f, "neg"),
+        }
+    }
+}
+
+fn compile(expr: &Expr) -> Result<Vec<Bytecode<'_>>, Error> {
+    enum Task<'a> {
+        Visit(&'a Expr),
+        Emit(Bytecode<'a>),
+    }
+
+    let mut code = Vec::new();
+    let mut tasks = vec![Task::Visit(expr)];
+
+    while let Some(task) = tasks.pop() {
+        match task {
+            Task::Emit(instruction) => code.push(instruction),
+            Task::Visit(Expr::Literal(offset, n)) => code.push(Bytecode::Push(*offset, n.clone())),
+            Task::Visit(Expr::Var(offset, name)) => {
+                code.push(Bytecode::Load(*offset, name.clone()))
+            }
+            Task::Visit(node @ Expr::Neg(_, operand)) => {
+                tasks.push(Task::Emit(Bytecode::Neg(node)));
+                tasks.push(Task::Visit(operand));
+            }
+            Task::Visit(node @ Expr::BinOp(op, offset, lhs, rhs)) => {
+                let instruction = match op {
+                    '+' => Bytecode::Add(node),
+                    '-' => Bytecode::Sub(node),
+                    '*' => Bytecode::Mul(node),
+                    '/' => Bytecode::Div(node),
+                    '%' => Bytecode::Rem(node),
+                    '^' => Bytecode::Pow(node),
+                    op => return Err(Error::new(*offset, ErrorKind::UnknownOperator(*op))),
+                };
+
+                tasks.push(Task::Emit(instruction));
+                tasks.push(Task::Visit(rhs));
+                tasks.push(Task::Visit(lhs));
+            }
+        }
+    }
+
+    Ok(code)
 }
 
-fn eval(expr: Expr) -> u64 {
+fn interpret<N: Number>(code: &[Bytecode], env: &Env) -> Result<N, Error> {
+    let mut stack = Vec::new();
+
+    for instruction in code {
+        let (op, node) = match *instruction {
+            Bytecode::Push(offset, ref n) => {
+                let overflow = || Error::new(offset, ErrorKind::Overflow(n.to_string()));
+                stack.push(N::literal(n).ok_or_else(overflow)?);
+                continue;
+            }
+            Bytecode::Load(offset, ref name) => {
+                stack.push(load(env, offset, name)?);
+                continue;
+            }
+            Bytecode::Neg(node) => {
+                let overflow = || Error::new(node.offset(), ErrorKind::Overflow("-".to_owned()));
+                let operand = stack.pop().unwrap();
+                stack.push(operand.checked_neg().ok_or_else(overflow)?);
+                continue;
+            }
+            Bytecode::Add(node) => ('+', node),
+            Bytecode::Sub(node) => ('-', node),
+            Bytecode::Mul(node) => ('*', node),
+            Bytecode::Div(node) => ('/', node),
+            Bytecode::Rem(node) => ('%', node),
+            Bytecode::Pow(node) => ('^', node),
+        };
+
+        let rhs = stack.pop().unwrap();
+        let lhs = stack.pop().unwrap();
+
+        let result = match op {
+            '/' | '%' if rhs.is_zero() => Err(ErrorKind::DivisionByZero),
+            '^' if rhs.is_negative() => Err(ErrorKind::NegativeExponent),
+            '^' if N::exponent_too_large(&lhs, &rhs) => Err(ErrorKind::ExponentTooLarge),
+            _ => N::checked_op(op, lhs, rhs).ok_or_else(|| ErrorKind::Overflow(op.to_string())),
+        };
+
+        stack.push(result.map_err(|kind| Error::new(node.offset(), kind))?);
+    }
+
+    Ok(stack.pop().unwrap())
+}
+
+fn small_literal(expr: &Expr) -> Option<i64> {
     match expr {
-        Expr::BinOp('+', lhs, rhs) => eval(*lhs) + eval(*rhs),
-        Expr::BinOp('*', lhs, rhs) => eval(*lhs) * eval(*rhs),
-        Expr::Literal(n) => n,
-        _ => panic!(),
+        Expr::Literal(_, n) => n.to_i64(),
+        _ => None,
+    }
+}
+
+fn simplify(expr: &Expr) -> Expr {
+    enum Task<'a> {
+        Visit(&'a Expr),
+        Negate(Offset),
+        Combine(char, Offset),
+    }
+
+    let mut output = Vec::new();
+    let mut tasks = vec![Task::Visit(expr)];
+
+    while let Some(task) = tasks.pop() {
+        match task {
+            Task::Visit(Expr::Literal(offset, n)) => output.push(Expr::Literal(*offset, n.clone())),
+            Task::Visit(Expr::Var(offset, name)) => output.push(Expr::Var(*offset, name.clone())),
+            Task::Visit(Expr::Neg(offset, operand)) => {
+                tasks.push(Task::Negate(*offset));
+                tasks.push(Task::Visit(operand));
+            }
+            Task::Visit(Expr::BinOp(op, offset, lhs, rhs)) => {
+                tasks.push(Task::Combine(*op, *offset));
+                tasks.push(Task::Visit(rhs));
+                tasks.push(Task::Visit(lhs));
+            }
+
+            Task::Negate(offset) => {
+                let operand = output.pop().unwrap();
+                output.push(match small_literal(&operand).and_then(i64::checked_neg) {
+                    Some(n) => Expr::Literal(offset, BigInt::from(n)),
+                    None => Expr::Neg(offset, Box::new(operand)),
+                });
+            }
+
+            Task::Combine(op, offset) => {
+                let rhs = output.pop().unwrap();
+                let lhs = output.pop().unwrap();
+
+                let folded = match (small_literal(&lhs), small_literal(&rhs)) {
+                    (Some(a), Some(b)) => i64::checked_op(op, a, b),
+                    _ => None,
+                };
+
+                let is = |expr, n| small_literal(expr) == Some(n);
+                output.push(match (folded, op) {
+                    (Some(n), _) => Expr::Literal(offset, BigInt::from(n)),
+                    (None, '+') if is(&lhs, 0) => rhs,
+                    (None, '*') if is(&lhs, 1) => rhs,
+                    (None, '+') | (None, '-') if is(&rhs, 0) => lhs,
+                    (None, '*') | (None, '/') | (None, '^') if is(&rhs, 1) => lhs,
+                    _ => Expr::BinOp(op, offset, Box::new(lhs), Box::new(rhs)),
+                });
+            }
+        }
+    }
+
+    output.pop().unwrap()
+}
+
+fn needs_parens(operators: &OperatorTable, op: char, child: &Expr, side: Associativity) -> bool {
+    let (precedence, associativity) = operators.get(op).unwrap_or((0, Associativity::Left));
+
+    match child {
+        Expr::BinOp(child_op, ..) => {
+            let (child_precedence, child_associativity) =
+                operators.get(*child_op).unwrap_or((0, Associativity::Left));
+
+            let deciding_associativity = match side {
+                Associativity::Left => associativity,
+                Associativity::Right => child_associativity,
+            };
+
+            child_precedence < precedence
+                || (child_precedence == precedence && deciding_associativity != side)
+        }
+        Expr::Literal(_, n) if !n.is_negative() => false,
+        Expr::Neg(..) | Expr::Literal(..) => {
+            side == Associativity::Left
+                && operators.binding_power(NEGATION) < operators.binding_power(op)
+        }
+        _ => false,
+    }
+}
+
+fn pretty(expr: &Expr, operators: Option<&OperatorTable>) -> String {
+    enum Task<'a> {
+        Visit(&'a Expr),
+        Text(&'static str),
+        Op(char),
+    }
+
+    let mut output = String::new();
+    let mut tasks = vec![Task::Visit(expr)];
+
+    while let Some(task) = tasks.pop() {
+        let expr = match task {
+            Task::Visit(expr) => expr,
+            Task::Text(text) => {
+                output.push_str(text);
+                continue;
+            }
+            Task::Op(op) => {
+                output.push(' ');
+                output.push(op);
+                output.push(' ');
+                continue;
+            }
+        };
+
+        match (expr, operators) {
+            (Expr::Literal(_, n), None) if n.is_negative() => output.push_str(&format!("({})", n)),
+            (Expr::Literal(_, n), _) => output.push_str(&n.to_string()),
+            (Expr::Var(_, name), _) => output.push_str(name),
+
+            (Expr::Neg(_, operand), None) => tasks.extend(vec![
+                Task::Text(")"),
+                Task::Visit(operand),
+                Task::Text("(-"),
+            ]),
+            (Expr::Neg(_, operand), Some(_)) => match **operand {
+                Expr::BinOp(..) => tasks.extend(vec![
+                    Task::Text(")"),
+                    Task::Visit(operand),
+                    Task::Text("-("),
+                ]),
+                _ => tasks.extend(vec![Task::Visit(operand), Task::Text("-")]),
+            },
+
+            (Expr::BinOp(op, _, lhs, rhs), None) => tasks.extend(vec![
+                Task::Text(")"),
+                Task::Visit(rhs),
+                Task::Op(*op),
+                Task::Visit(lhs),
+                Task::Text("("),
+            ]),
+            (Expr::BinOp(op, _, lhs, rhs), Some(operators)) => {
+                for (child, side) in &[(rhs, Associativity::Right), (lhs, Associativity::Left)] {
+                    if needs_parens(operators, *op, child, *side) {
+                        tasks.extend(vec![Task::Text(")"), Task::Visit(child), Task::Text("(")]);
+                    } else {
+                        tasks.push(Task::Visit(child));
+                    }
+
+                    if *side == Associativity::Right {
+                        tasks.push(Task::Op(*op));
+                    }
+                }
+            }
+        }
+    }
+
+    output
+}
+
+fn execute<N: Number + fmt::Display>(statement: &Statement, env: &mut Env) -> Result<N, Error> {
+    let expr = statement.expr();
+    let value = interpret::<N>(&compile(&simplify(expr))?, env)?;
+    if let Statement::Let(name, _) = statement {
+        env.insert(name.clone(), value.to_big());
+    }
+    Ok(value)
+}
+
+fn results_sum<N: Number + fmt::Display>(input: &str, operators: &OperatorTable) -> Option<N> {
+    let mut env = Env::new();
+    let mut results = Vec::new();
+
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let result = parse_statement(line, operators)
+            .and_then(|statement| Ok((execute::<N>(&statement, &mut env)?, statement)));
+
+        match result {
+            Ok((result, Statement::Expr(_))) => results.push(result),
+            Ok((_, Statement::Let(..))) => {}
+            Err(e) => eprintln!("line {}:\n{}", i + 1, e.report(line)),
+        }
     }
+
+    results
+        .into_iter()
+        .try_fold(N::zero(), |sum, result| N::checked_op('+', sum, result))
+}
+
+fn tree(expr: &Expr, depth: usize) -> String {
+    let mut output = String::new();
+    let mut nodes = vec![(expr, depth)];
+
+    while let Some((expr, depth)) = nodes.pop() {
+        output.push_str(&"  ".repeat(depth));
+
+        match expr {
+            Expr::Literal(_, n) => output.push_str(&n.to_string()),
+            Expr::Var(_, name) => output.push_str(name),
+            Expr::Neg(_, operand) => {
+                output.push_str("neg");
+                nodes.push((operand, depth + 1));
+            }
+            Expr::BinOp(op, _, lhs, rhs) => {
+                output.push(*op);
+                nodes.push((rhs, depth + 1));
+                nodes.push((lhs, depth + 1));
+            }
+        }
+
+        output.push('\n');
+    }
+
+    output
 }
 
-fn results_sum(input: &str, precedence: impl Fn(char) -> u8) -> u64 {
-    input
-        .lines()
-        .map(|line| parse(line, &precedence))
-        .map(eval)
-        .sum::<u64>()
+const REPL_HELP: &str = "\
+enter an expression to evaluate it, `let <name> = <expression>` to bind it, or one of:
+  :tables <name>...   evaluate under these tables (one, two, standard, or loaded ones)
+  :load <name> <path> load an operator table from a file
+  :tree               toggle showing the parsed tree
+  :big                toggle big-integer evaluation
+  :sum <path>         sum every line of a file under the active tables
+  :quit               leave
+";
+
+struct Repl {
+    tables: Vec<(String, OperatorTable)>,
+    active: Vec<usize>,
+    envs: HashMap<String, Env>,
+    show_tree: bool,
+    big: bool,
+}
+
+impl Repl {
+    fn new() -> Self {
+        Self {
+            tables: vec![
+                ("one".to_owned(), OperatorTable::part_one()),
+                ("two".to_owned(), OperatorTable::part_two()),
+                ("standard".to_owned(), OperatorTable::standard()),
+            ],
+            active: vec![0, 1],
+            envs: HashMap::new(),
+            show_tree: false,
+            big: false,
+        }
+    }
+
+    fn run(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.split(b'\n') {
+            let line = match String::from_utf8(line?) {
+                Ok(line) => line,
+                Err(e) => {
+                    writeln!(output, "{}", e)?;
+                    continue;
+                }
+            };
+            let line = line.trim_end_matches('\r');
+            let mut words = line.split_whitespace();
+
+            match words.next() {
+                None => {}
+                Some(":quit") => break,
+                Some(":tree") => self.show_tree = !self.show_tree,
+                Some(":big") => self.big = !self.big,
+                Some(":tables") => self.select(words, &mut output)?,
+                Some(":load") => match (words.next(), words.next()) {
+                    (Some(name), Some(path)) => self.load(name, path, &mut output)?,
+                    _ => write!(output, "{}", REPL_HELP)?,
+                },
+                Some(":sum") => match words.next().map(std::fs::read_to_string) {
+                    Some(Ok(contents)) => self.sum(&contents, &mut output)?,
+                    Some(Err(e)) => writeln!(output, "{}", e)?,
+                    None => write!(output, "{}", REPL_HELP)?,
+                },
+                Some(command) if command.starts_with(':') => write!(output, "{}", REPL_HELP)?,
+                Some(_) => self.evaluate(line, &mut output)?,
+            }
+        }
+
+        output.flush()
+    }
+
+    fn select<'a>(
+        &mut self,
+        names: impl Iterator<Item = &'a str>,
+        output: &mut impl Write,
+    ) -> io::Result<()> {
+        let mut active = Vec::new();
+
+        for name in names {
+            match self.tables.iter().position(|(table, _)| table == name) {
+                Some(i) => active.push(i),
+                None => return writeln!(output, "unknown table `{}`", name),
+            }
+        }
+
+        self.active = active;
+        Ok(())
+    }
+
+    fn load(&mut self, name: &str, path: &str, output: &mut impl Write) -> io::Result<()> {
+        let table = match std::fs::read_to_string(path).map(|spec| spec.parse()) {
+            Ok(Ok(table)) => table,
+            Ok(Err(())) => return writeln!(output, "invalid operator table"),
+            Err(e) => return writeln!(output, "{}", e),
+        };
+
+        self.tables.retain(|(table, _)| table != name);
+        self.envs.remove(name);
+        self.tables.push((name.to_owned(), table));
+        self.active = vec![self.tables.len() - 1];
+        Ok(())
+    }
+
+    fn evaluate(&mut self, line: &str, output: &mut impl Write) -> io::Result<()> {
+        for &i in &self.active {
+            let (name, operators) = &self.tables[i];
+
+            let statement = match parse_statement(line, operators) {
+                Ok(statement) => statement,
+                Err(e) => {
+                    writeln!(output, "{}:\n{}", name, e.report(line))?;
+                    continue;
+                }
+            };
+
+            let env = self.envs.entry(name.clone()).or_default();
+            let result = if self.big {
+                execute::<BigInt>(&statement, env).map(|n| n.to_string())
+            } else {
+                execute::<i64>(&statement, env).map(|n| n.to_string())
+            };
+
+            match (result, &statement) {
+                (Ok(result), Statement::Let(var, _)) => {
+                    writeln!(output, "{}: {} = {}", name, var, result)?
+                }
+                (Ok(result), Statement::Expr(_)) => writeln!(output, "{}: {}", name, result)?,
+                (Err(e), _) => writeln!(output, "{}:\n{}", name, e.report(line))?,
+            }
+
+            if self.show_tree {
+                write!(output, "{}", tree(statement.expr(), 1))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn sum(&self, input: &str, output: &mut impl Write) -> io::Result<()> {
+        for &i in &self.active {
+            let (name, operators) = &self.tables[i];
+
+            let sum = if self.big {
+                results_sum::<BigInt>(input, operators).map(|n| n.to_string())
+            } else {
+                results_sum::<i64>(input, operators).map(|n| n.to_string())
+            };
+
+            match sum {
+                Some(sum) => writeln!(output, "{}: {}", name, sum)?,
+                None => writeln!(output, "{}: the sum overflows, try :big", name)?,
+            }
+        }
+
+        Ok(())
+    }
 }
 
 fn main() {
     let input = include_str!("../../inputs/day18.txt");
+    let (flags, args) = std::env::args()
+        .skip(1)
+        .partition::<Vec<_>, _>(|arg| arg.starts_with("--"));
+
+    let tables = match args.first() {
+        Some(path) => vec![std::fs::read_to_string(path)
+            .unwrap()
+            .parse()
+            .expect("invalid operator table")],
+        None => vec![OperatorTable::part_one(), OperatorTable::part_two()],
+    };
+
+    let render: Box<dyn Fn(&Expr) -> String> = match flags.first().map(String::as_str) {
+        None => {
+            for operators in &tables {
+                match results_sum::<i64>(input, operators) {
+                    Some(sum) => println!("{}", sum),
+                    None => println!("the sum overflows, try --big"),
+                }
+            }
+            return;
+        }
+        Some("--big") => {
+            for operators in &tables {
+                println!("{}", results_sum::<BigInt>(input, operators).unwrap());
+            }
+            return;
+        }
+        Some("--repl") => {
+            let stdin = std::io::stdin();
+            Repl::new().run(stdin.lock(), std::io::stdout()).unwrap();
+            return;
+        }
+        Some("--show") => {
+            let standard = OperatorTable::standard();
+            Box::new(move |expr: &Expr| pretty(expr, Some(&standard)))
+        }
+        Some("--show-parenthesized") => Box::new(|expr: &Expr| pretty(expr, None)),
+        Some("--simplify") => {
+            let standard = OperatorTable::standard();
+            Box::new(move |expr: &Expr| pretty(&simplify(expr), Some(&standard)))
+        }
+        Some("--bytecode") => Box::new(|expr: &Expr| match compile(expr) {
+            Ok(code) => code
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n    "),
+            Err(e) => e.to_string(),
+        }),
+        Some(flag) => panic!("unknown flag: {}", flag),
+    };
+
+    for line in input.lines() {
+        println!("{}", line);
+
+        for operators in &tables {
+            match parse_statement(line, operators) {
+                Ok(Statement::Let(name, expr)) => println!("    let {} = {}", name, render(&expr)),
+                Ok(Statement::Expr(expr)) => println!("    {}", render(&expr)),
+                Err(e) => println!("    {}", e),
+            }
+        }
+    }
+}
 
-    println!(
-        "{}",
-        results_sum(input, |op| match op {
-            '+' => 1,
-            '*' => 1,
-            _ => 0,
-        })
-    );
-
-    println!(
-        "{}",
-        results_sum(input, |op| match op {
-            '+' => 2,
-            '*' => 1,
-            _ => 0,
-        })
-    );
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval<N: Number>(expr: &Expr, env: &Env) -> Result<N, Error> {
+        let overflow = |offset, op: char| Error::new(offset, ErrorKind::Overflow(op.to_string()));
+
+        match expr {
+            Expr::BinOp(op, offset, lhs, rhs) => {
+                let (lhs, rhs) = (eval::<N>(lhs, env)?, eval::<N>(rhs, env)?);
+                match op {
+                    '/' | '%' if rhs.is_zero() => {
+                        Err(Error::new(*offset, ErrorKind::DivisionByZero))
+                    }
+                    '^' if rhs.is_negative() => {
+                        Err(Error::new(*offset, ErrorKind::NegativeExponent))
+                    }
+                    '^' if N::exponent_too_large(&lhs, &rhs) => {
+                        Err(Error::new(*offset, ErrorKind::ExponentTooLarge))
+                    }
+                    '+' | '-' | '*' | '/' | '%' | '^' => {
+                        N::checked_op(*op, lhs, rhs).ok_or_else(|| overflow(*offset, *op))
+                    }
+                    op => Err(Error::new(*offset, ErrorKind::UnknownOperator(*op))),
+                }
+            }
+            Expr::Neg(offset, operand) => eval::<N>(operand, env)?
+                .checked_neg()
+                .ok_or_else(|| overflow(*offset, '-')),
+            Expr::Literal(offset, n) => {
+                N::literal(n).ok_or_else(|| Error::new(*offset, ErrorKind::Overflow(n.to_string())))
+            }
+            Expr::Var(offset, name) => load(env, *offset, name),
+        }
+    }
+
+    const CORPUS: &[&str] = &[
+        "1 + 2 * 3",
+        "7 - 10 / 3 % 2",
+        "(1 + 2) * (3 - 4) ^ 2",
+        "2 ^ 3 ^ 2",
+        "-2 ^ 2",
+        "(-2) ^ 2",
+        "-(3 - 5) * -4",
+        "--5 + -x",
+        "x * 1 + 0 - y / 1",
+        "-7 / 2 + -7 % 2",
+        "10 / 0",
+        "10 % (5 - 5)",
+        "2 ^ -1",
+        "2 ^ (0 - 1)",
+        "9223372036854775807 + 1",
+        "0 - 9223372036854775807 - 2",
+        "3037000500 * 3037000500",
+        "2 ^ 63",
+        "-(0 - 9223372036854775807 - 1)",
+        "99999999999999999999 - 99999999999999999998",
+        "y * 100000000000",
+        "z + 1",
+        "(-1) ^ 5000000001 + 0 ^ 5000000000",
+        "1 ^ 99999999999999999999",
+        "1 ^ (0 - 1)",
+        "2 ^ 4000000000",
+    ];
+
+    fn env() -> Env {
+        let mut env = Env::new();
+        env.insert("x".to_owned(), BigInt::from(-3));
+        env.insert("y".to_owned(), BigInt::from(i64::MAX));
+        env
+    }
+
+    fn check<N: Number + fmt::Display>(operators: &OperatorTable) {
+        let env = env();
+
+        for line in CORPUS {
+            let expr = parse_statement(line, operators).unwrap();
+            let expr = expr.expr();
+            let expected = eval::<N>(expr, &env);
+
+            assert_eq!(
+                interpret::<N>(&compile(expr).unwrap(), &env),
+                expected,
+                "{}",
+                line
+            );
+            assert_eq!(
+                interpret::<N>(&compile(&simplify(expr)).unwrap(), &env).ok(),
+                expected.ok(),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn bytecode_agrees_with_eval() {
+        for operators in &[
+            OperatorTable::part_one(),
+            OperatorTable::part_two(),
+            OperatorTable::standard(),
+        ] {
+            check::<i64>(operators);
+            check::<BigInt>(operators);
+        }
+    }
+
+    #[test]
+    fn errors_point_at_the_failing_operation() {
+        let operators = OperatorTable::standard();
+        let run = |line| {
+            let expr = parse_statement(line, &operators).unwrap();
+            interpret::<i64>(&compile(expr.expr()).unwrap(), &env())
+        };
+
+        assert_eq!(
+            run("1 + 10 / 0"),
+            Err(Error::new(7, ErrorKind::DivisionByZero))
+        );
+        assert_eq!(
+            run("2 ^ -1"),
+            Err(Error::new(2, ErrorKind::NegativeExponent))
+        );
+        assert_eq!(
+            run("1 + (y + 1) * 2"),
+            Err(Error::new(7, ErrorKind::Overflow("+".to_owned())))
+        );
+        assert_eq!(
+            run("10000000000000000000"),
+            Err(Error::new(
+                0,
+                ErrorKind::Overflow("10000000000000000000".to_owned())
+            ))
+        );
+        assert_eq!(
+            run("z"),
+            Err(Error::new(0, ErrorKind::UnknownVariable("z".to_owned())))
+        );
+    }
+
+    #[test]
+    fn reports_mark_far_offsets() {
+        let operators = OperatorTable::standard();
+        let line = format!("{}1 / 0", "1 + ".repeat(20_000));
+        let expr = parse_statement(&line, &operators).unwrap();
+        let error = interpret::<i64>(&compile(expr.expr()).unwrap(), &env()).unwrap_err();
+
+        assert_eq!(error.offset, 80_002);
+        assert_eq!(
+            error.report(&line),
+            format!(
+                "{}\n{}^\ndivision by zero at offset 80002",
+                line,
+                " ".repeat(80_002)
+            )
+        );
+    }
+
+    #[test]
+    fn negation_binds_below_exponentiation() {
+        let operators = OperatorTable::standard();
+        let run = |line| {
+            let expr = parse_statement(line, &operators).unwrap();
+            eval::<i64>(expr.expr(), &Env::new())
+        };
+
+        assert_eq!(run("-2 ^ 2"), Ok(-4));
+        assert_eq!(run("(-2) ^ 2"), Ok(4));
+        assert_eq!(run("-2 * 3"), Ok(-6));
+        assert_eq!(
+            run("2 ^ -1 + 1"),
+            Err(Error::new(2, ErrorKind::NegativeExponent))
+        );
+    }
+
+    #[test]
+    fn powers_with_huge_exponents() {
+        let operators = OperatorTable::standard();
+        let parse = |line| parse_statement(line, &operators).unwrap();
+        let small = |line| interpret::<i64>(&compile(parse(line).expr()).unwrap(), &env());
+        let big = |line| interpret::<BigInt>(&compile(parse(line).expr()).unwrap(), &env());
+
+        assert_eq!(small("1 ^ 5000000000"), Ok(1));
+        assert_eq!(small("(-1) ^ 5000000001"), Ok(-1));
+        assert_eq!(small("x ^ 0 + 0 ^ 0"), Ok(2));
+        assert_eq!(big("1 ^ 99999999999999999999"), Ok(BigInt::from(1)));
+        assert_eq!(big("(-1) ^ 99999999999999999999"), Ok(BigInt::from(-1)));
+        assert_eq!(big("0 ^ 99999999999999999999"), Ok(BigInt::from(0)));
+        assert_eq!(
+            big("2 ^ 4000000000"),
+            Err(Error::new(2, ErrorKind::ExponentTooLarge))
+        );
+        assert_eq!(
+            big("x ^ 99999999999999999999"),
+            Err(Error::new(2, ErrorKind::ExponentTooLarge))
+        );
+        assert!(matches!(
+            small("2 ^ 4000000000"),
+            Err(Error {
+                kind: ErrorKind::Overflow(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn repl_session() {
+        let script = b"let a = 2 + 3 * 4\na * 2\n\xff\n:tables standard\n:big\n\
+                       let a = 2 ^ 70\n:bogus\n1 +\n:quit\n1\n";
+        let mut output = Vec::new();
+        Repl::new().run(&script[..], &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "one: a = 20\n\
+                 two: a = 20\n\
+                 one: 40\n\
+                 two: 40\n\
+                 invalid utf-8 sequence of 1 bytes from index 0\n\
+                 standard: a = 1180591620717411303424\n\
+                 {}\
+                 standard:\n\
+                 1 +\n   \
+                 ^\n\
+                 missing operand at offset 3\n",
+                REPL_HELP
+            )
+        );
+    }
+
+    #[test]
+    fn deeply_nested_expressions() {
+        let operators = OperatorTable::standard();
+        let depth = 100_000;
+        let line = format!("y + {}1{}", "(1 * ".repeat(depth), ")".repeat(depth));
+        let expr = parse_statement(&line, &operators).unwrap();
+
+        assert_eq!(
+            interpret::<i64>(&compile(expr.expr()).unwrap(), &env()),
+            Err(Error::new(2, ErrorKind::Overflow("+".to_owned())))
+        );
+        assert_eq!(
+            pretty(expr.expr(), Some(&operators)),
+            format!(
+                "y + {}1 * 1{}",
+                "1 * (".repeat(depth - 1),
+                ")".repeat(depth - 1)
+            )
+        );
+        assert_eq!(pretty(expr.expr(), None), format!("({})", line));
+    }
+
+    #[test]
+    fn pretty_printing_round_trips() {
+        for operators in &[OperatorTable::part_two(), OperatorTable::standard()] {
+            for line in CORPUS {
+                let expr = parse_statement(line, operators).unwrap();
+                let expected = eval::<BigInt>(expr.expr(), &env()).ok();
+
+                for text in &[
+                    pretty(expr.expr(), Some(operators)),
+                    pretty(expr.expr(), None),
+                    pretty(&simplify(expr.expr()), Some(operators)),
+                ] {
+                    let reparsed = parse_statement(text, operators).unwrap();
+                    assert_eq!(
+                        eval::<BigInt>(reparsed.expr(), &env()).ok(),
+                        expected,
+                        "{}",
+                        text
+                    );
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,166 @@
+pub mod asm;
+pub mod cfg;
+pub mod debugger;
+pub mod repair;
+pub mod trace;
+
+use std::{fmt, str::FromStr};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Acc(i32),
+    Jmp(i32),
+    Nop(i32),
+}
+
+impl Instruction {
+    pub fn new(op: &str, val: i32) -> Option<Self> {
+        match op {
+            "acc" => Some(Self::Acc(val)),
+            "jmp" => Some(Self::Jmp(val)),
+            "nop" => Some(Self::Nop(val)),
+            _ => None,
+        }
+    }
+
+    pub fn flipped(self) -> Option<Self> {
+        match self {
+            Self::Acc(_) => None,
+            Self::Jmp(val) => Some(Self::Nop(val)),
+            Self::Nop(val) => Some(Self::Jmp(val)),
+        }
+    }
+
+    pub fn successor(self, index: i32) -> i32 {
+        match self {
+            Self::Jmp(val) => index + val,
+            Self::Acc(_) | Self::Nop(_) => index + 1,
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ' ');
+        let op = parts.next().ok_or(())?;
+        let val = parts.next().ok_or(())?.parse().map_err(|_| ())?;
+
+        Self::new(op, val).ok_or(())
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Acc(val) => write!(f, "acc {:+}", val),
+            Self::Jmp(val) => write!(f, "jmp {:+}", val),
+            Self::Nop(val) => write!(f, "nop {:+}", val),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Halt {
+    Terminated,
+    InfiniteLoop,
+    OutOfBounds,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Step {
+    pub index: usize,
+    pub instruction: Instruction,
+    pub accumulator: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Machine {
+    program: Vec<Instruction>,
+    accumulator: i32,
+    program_counter: i32,
+    executed: Vec<bool>,
+}
+
+impl Machine {
+    pub fn new(program: Vec<Instruction>) -> Self {
+        Self {
+            executed: vec![false; program.len()],
+            program,
+            accumulator: 0,
+            program_counter: 0,
+        }
+    }
+
+    pub fn program(&self) -> &[Instruction] {
+        &self.program
+    }
+
+    pub fn toggle(&mut self, index: usize) -> Option<Instruction> {
+        let instruction = self.program.get_mut(index)?;
+        *instruction = instruction.flipped()?;
+        Some(*instruction)
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(std::mem::take(&mut self.program));
+    }
+
+    pub fn accumulator(&self) -> i32 {
+        self.accumulator
+    }
+
+    pub fn program_counter(&self) -> i32 {
+        self.program_counter
+    }
+
+    pub fn status(&self) -> Option<Halt> {
+        let len = self.program.len() as i32;
+
+        if self.program_counter == len {
+            Some(Halt::Terminated)
+        } else if !(0..len).contains(&self.program_counter) {
+            Some(Halt::OutOfBounds)
+        } else if self.executed[self.program_counter as usize] {
+            Some(Halt::InfiniteLoop)
+        } else {
+            None
+        }
+    }
+
+    pub fn step(&mut self) -> Result<Step, Halt> {
+        if let Some(halt) = self.status() {
+            return Err(halt);
+        }
+
+        let index = self.program_counter as usize;
+        let instruction = self.program[index];
+        self.executed[index] = true;
+
+        if let Instruction::Acc(val) = instruction {
+            self.accumulator += val;
+        }
+
+        self.program_counter = instruction.successor(self.program_counter);
+
+        Ok(Step {
+            index,
+            instruction,
+            accumulator: self.accumulator,
+        })
+    }
+
+    pub fn run(&mut self) -> Halt {
+        self.run_with(|_| {})
+    }
+
+    pub fn run_with(&mut self, mut on_step: impl FnMut(&Step)) -> Halt {
+        loop {
+            match self.step() {
+                Ok(step) => on_step(&step),
+                Err(halt) => return halt,
+            }
+        }
+    }
+}
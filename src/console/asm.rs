@@ -0,0 +1,163 @@
+use std::{collections::HashMap, fmt};
+
+use super::Instruction;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AssemblyError {
+    InvalidInstruction { line: usize },
+    UnknownLabel { line: usize, label: String },
+    DuplicateLabel { line: usize, label: String },
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidInstruction { line } => write!(f, "line {}: invalid instruction", line),
+            Self::UnknownLabel { line, label } => {
+                write!(f, "line {}: unknown label `{}`", line, label)
+            }
+            Self::DuplicateLabel { line, label } => {
+                write!(f, "line {}: label `{}` is already defined", line, label)
+            }
+        }
+    }
+}
+
+fn is_label(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub fn assemble(source: &str) -> Result<Vec<Instruction>, AssemblyError> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let mut statement = line.trim();
+
+        if let Some(colon) = statement.find(':') {
+            let label = statement[..colon].trim();
+            if !is_label(label) {
+                return Err(AssemblyError::InvalidInstruction { line: line_number });
+            }
+
+            if labels.insert(label, statements.len()).is_some() {
+                return Err(AssemblyError::DuplicateLabel {
+                    line: line_number,
+                    label: label.to_owned(),
+                });
+            }
+
+            statement = statement[colon + 1..].trim();
+        }
+
+        if !statement.is_empty() {
+            statements.push((line_number, statement));
+        }
+    }
+
+    statements
+        .iter()
+        .enumerate()
+        .map(|(index, &(line, statement))| {
+            let mut parts = statement.split_whitespace();
+            let (op, operand) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(op), Some(operand), None) => (op, operand),
+                _ => return Err(AssemblyError::InvalidInstruction { line }),
+            };
+
+            let val = if is_label(operand) && op != "acc" {
+                let target = labels
+                    .get(operand)
+                    .ok_or_else(|| AssemblyError::UnknownLabel {
+                        line,
+                        label: operand.to_owned(),
+                    })?;
+
+                *target as i32 - index as i32
+            } else {
+                operand
+                    .parse()
+                    .map_err(|_| AssemblyError::InvalidInstruction { line })?
+            };
+
+            Instruction::new(op, val).ok_or(AssemblyError::InvalidInstruction { line })
+        })
+        .collect()
+}
+
+pub fn disassemble(program: &[Instruction]) -> String {
+    program
+        .iter()
+        .map(|instruction| format!("{}\n", instruction))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Instruction::*;
+
+    #[test]
+    fn numeric_round_trip() {
+        let program = vec![Nop(0), Acc(1), Jmp(4), Acc(3), Jmp(-3), Acc(-99), Nop(-4)];
+        assert_eq!(
+            disassemble(&program),
+            "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nnop -4\n"
+        );
+        assert_eq!(assemble(&disassemble(&program)), Ok(program));
+    }
+
+    #[test]
+    fn forward_and_backward_labels() {
+        let source = "start: acc +1\n  jmp skip\n  acc +2\nskip:\n  nop start\n  jmp start\n";
+        assert_eq!(
+            assemble(source),
+            Ok(vec![Acc(1), Jmp(2), Acc(2), Nop(-3), Jmp(-4)])
+        );
+    }
+
+    #[test]
+    fn trailing_label_resolves_to_program_length() {
+        assert_eq!(assemble("jmp end\nacc +1\nend:"), Ok(vec![Jmp(2), Acc(1)]));
+    }
+
+    #[test]
+    fn unknown_label() {
+        assert_eq!(
+            assemble("acc +1\njmp nowhere"),
+            Err(AssemblyError::UnknownLabel {
+                line: 2,
+                label: "nowhere".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn duplicate_label() {
+        assert_eq!(
+            assemble("a: acc +1\nnop +0\na: jmp a"),
+            Err(AssemblyError::DuplicateLabel {
+                line: 3,
+                label: "a".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_instruction_reports_its_line() {
+        for (source, line) in &[
+            ("acc +1\nmul +2", 2),
+            ("nop +0\n\nacc", 3),
+            ("acc +1\njmp +1 +2", 2),
+            ("acc +1\nacc +1\nacc x", 3),
+            ("1abel: nop +0", 1),
+        ] {
+            assert_eq!(
+                assemble(source),
+                Err(AssemblyError::InvalidInstruction { line: *line })
+            );
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt::Write;
+
+use super::Instruction;
+
+pub struct ControlFlowGraph<'a> {
+    program: &'a [Instruction],
+    successors: Vec<Option<usize>>,
+}
+
+impl<'a> ControlFlowGraph<'a> {
+    pub fn new(program: &'a [Instruction]) -> Self {
+        let len = program.len() as i32;
+
+        let successors = program
+            .iter()
+            .enumerate()
+            .map(|(i, instruction)| {
+                let successor = instruction.successor(i as i32);
+                if (0..=len).contains(&successor) {
+                    Some(successor as usize)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            program,
+            successors,
+        }
+    }
+
+    pub fn end(&self) -> usize {
+        self.program.len()
+    }
+
+    pub fn successor(&self, index: usize) -> Option<usize> {
+        self.successors.get(index).copied().flatten()
+    }
+
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.program.len() + 1];
+        let mut index = Some(0);
+
+        while let Some(i) = index.filter(|&i| !reachable[i]) {
+            reachable[i] = true;
+            index = self.successor(i);
+        }
+
+        reachable
+    }
+
+    pub fn unreachable(&self) -> Vec<usize> {
+        let reachable = self.reachable();
+        (0..self.program.len()).filter(|&i| !reachable[i]).collect()
+    }
+
+    pub fn out_of_bounds_jumps(&self) -> Vec<usize> {
+        (0..self.program.len())
+            .filter(|&i| self.successors[i].is_none())
+            .collect()
+    }
+
+    pub fn loops(&self) -> Vec<Vec<usize>> {
+        // Every instruction has a single successor, so the only strongly
+        // connected components that loop are simple cycles.
+        let mut walk_of = vec![None; self.program.len() + 1];
+        let mut loops = Vec::new();
+
+        for start in 0..self.program.len() {
+            let mut path = Vec::new();
+            let mut index = Some(start);
+
+            while let Some(i) = index.filter(|&i| walk_of[i].is_none()) {
+                walk_of[i] = Some(start);
+                path.push(i);
+                index = self.successor(i);
+            }
+
+            if let Some(i) = index.filter(|&i| walk_of[i] == Some(start)) {
+                let cycle_start = path.iter().position(|&j| j == i).unwrap();
+                loops.push(path.split_off(cycle_start));
+            }
+        }
+
+        loops
+    }
+
+    pub fn to_dot(&self) -> String {
+        let reachable = self.reachable();
+        let mut dot = String::from("digraph program {\n    node [shape=box];\n");
+
+        for (i, instruction) in self.program.iter().enumerate() {
+            let style = if reachable[i] { "" } else { ", color=gray" };
+            writeln!(
+                dot,
+                "    n{} [label=\"{}: {}\"{}];",
+                i, i, instruction, style
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            dot,
+            "    n{} [label=\"end\", shape=doublecircle];",
+            self.end()
+        )
+        .unwrap();
+
+        if self.successors.contains(&None) {
+            writeln!(dot, "    out_of_bounds [shape=octagon, color=red];").unwrap();
+        }
+
+        for (i, successor) in self.successors.iter().enumerate() {
+            match successor {
+                Some(successor) => writeln!(dot, "    n{} -> n{};", i, successor).unwrap(),
+                None => writeln!(dot, "    n{} -> out_of_bounds [color=red];", i).unwrap(),
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::asm::assemble;
+
+    fn program() -> Vec<Instruction> {
+        assemble("acc +1\njmp +2\nacc +5\njmp -3\njmp +10\njmp +0").unwrap()
+    }
+
+    #[test]
+    fn reachability() {
+        let program = program();
+        let cfg = ControlFlowGraph::new(&program);
+
+        assert_eq!(cfg.unreachable(), vec![2, 4, 5]);
+        assert_eq!(cfg.out_of_bounds_jumps(), vec![4]);
+        assert_eq!(cfg.successor(3), Some(0));
+        assert_eq!(cfg.successor(4), None);
+    }
+
+    #[test]
+    fn loops() {
+        let program = program();
+        assert_eq!(
+            ControlFlowGraph::new(&program).loops(),
+            vec![vec![0, 1, 3], vec![5]]
+        );
+
+        let terminating = assemble("nop +0\njmp +2\nacc +1\nacc +2").unwrap();
+        assert!(ControlFlowGraph::new(&terminating).loops().is_empty());
+    }
+
+    #[test]
+    fn to_dot() {
+        let program = assemble("nop +0\njmp +5\nacc +3").unwrap();
+
+        assert_eq!(
+            ControlFlowGraph::new(&program).to_dot(),
+            "digraph program {
+    node [shape=box];
+    n0 [label=\"0: nop +0\"];
+    n1 [label=\"1: jmp +5\"];
+    n2 [label=\"2: acc +3\", color=gray];
+    n3 [label=\"end\", shape=doublecircle];
+    out_of_bounds [shape=octagon, color=red];
+    n0 -> n1;
+    n1 -> out_of_bounds [color=red];
+    n2 -> n3;
+}
+"
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, BufRead, Write},
+};
+
+use super::{Halt, Machine, Step};
+
+const HELP: &str = "\
+commands:
+  s, step [n]      execute the next n instructions (default 1)
+  c, continue      run until a breakpoint or halt
+  b, break <i>     set a breakpoint on instruction i
+  d, delete <i>    remove the breakpoint on instruction i
+  p, print         show the program counter and accumulator
+  l, list [i]      show the instructions around i (default: program counter)
+  t, toggle <i>    swap instruction i between jmp and nop
+  r, reset         restart the program from the beginning
+  q, quit          leave the debugger
+";
+
+pub struct Debugger {
+    machine: Machine,
+    breakpoints: BTreeSet<usize>,
+}
+
+impl Debugger {
+    pub fn new(machine: Machine) -> Self {
+        Self {
+            machine,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        write!(output, "(dbg) ")?;
+        output.flush()?;
+
+        for line in input.lines() {
+            if !self.execute(&line?, &mut output)? {
+                return Ok(());
+            }
+
+            write!(output, "(dbg) ")?;
+            output.flush()?;
+        }
+
+        writeln!(output)
+    }
+
+    fn execute(&mut self, command: &str, output: &mut impl Write) -> io::Result<bool> {
+        let mut words = command.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let argument = words.next().map(str::parse::<usize>);
+
+        match (command, argument) {
+            ("", None) => {}
+
+            ("s", None) | ("step", None) => self.step(1, output)?,
+            ("s", Some(Ok(n))) | ("step", Some(Ok(n))) => self.step(n, output)?,
+
+            ("c", None) | ("continue", None) => loop {
+                let step = self.machine.step();
+                self.report(step, output)?;
+
+                let pc = self.machine.program_counter();
+                if step.is_err() || self.breakpoints.contains(&(pc as usize)) {
+                    break;
+                }
+            },
+
+            ("b", Some(Ok(i))) | ("break", Some(Ok(i))) => {
+                self.breakpoints.insert(i);
+                writeln!(output, "breakpoint at {}", i)?;
+            }
+
+            ("d", Some(Ok(i))) | ("delete", Some(Ok(i))) => {
+                if !self.breakpoints.remove(&i) {
+                    writeln!(output, "no breakpoint at {}", i)?;
+                }
+            }
+
+            ("p", None) | ("print", None) => {
+                writeln!(
+                    output,
+                    "pc = {}, acc = {}",
+                    self.machine.program_counter(),
+                    self.machine.accumulator()
+                )?;
+
+                if let Some(halt) = self.machine.status() {
+                    writeln!(output, "halted: {:?}", halt)?;
+                }
+            }
+
+            ("l", None) | ("list", None) => {
+                let pc = self.machine.program_counter().max(0) as usize;
+                self.list(pc, output)?;
+            }
+            ("l", Some(Ok(i))) | ("list", Some(Ok(i))) => self.list(i, output)?,
+
+            ("t", Some(Ok(i))) | ("toggle", Some(Ok(i))) => match self.machine.toggle(i) {
+                Some(instruction) => writeln!(output, "{}: {}", i, instruction)?,
+                None => writeln!(output, "instruction {} can't be toggled", i)?,
+            },
+
+            ("r", None) | ("reset", None) => self.machine.reset(),
+
+            ("q", None) | ("quit", None) => return Ok(false),
+
+            _ => write!(output, "{}", HELP)?,
+        }
+
+        Ok(true)
+    }
+
+    fn step(&mut self, n: usize, output: &mut impl Write) -> io::Result<()> {
+        for _ in 0..n {
+            let step = self.machine.step();
+            self.report(step, output)?;
+
+            if step.is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn report(&self, step: Result<Step, Halt>, output: &mut impl Write) -> io::Result<()> {
+        match step {
+            Ok(step) => writeln!(
+                output,
+                "{}: {}  acc = {}",
+                step.index, step.instruction, step.accumulator
+            ),
+            Err(halt) => writeln!(
+                output,
+                "halted at {}: {:?}",
+                self.machine.program_counter(),
+                halt
+            ),
+        }
+    }
+
+    fn list(&self, around: usize, output: &mut impl Write) -> io::Result<()> {
+        let program = self.machine.program();
+        let start = around.saturating_sub(3);
+        let end = around.saturating_add(4).min(program.len());
+
+        for (i, instruction) in program.iter().enumerate().take(end).skip(start) {
+            let pc = if i as i32 == self.machine.program_counter() {
+                ">"
+            } else {
+                " "
+            };
+            let breakpoint = if self.breakpoints.contains(&i) {
+                "*"
+            } else {
+                " "
+            };
+
+            writeln!(output, "{}{} {:>4}: {}", pc, breakpoint, i, instruction)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::asm::assemble;
+
+    fn session(source: &str, script: &str) -> String {
+        let machine = Machine::new(assemble(source).unwrap());
+        let mut output = Vec::new();
+        Debugger::new(machine)
+            .run(script.as_bytes(), &mut output)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    const PROGRAM: &str = "nop +0\nacc +1\njmp -2\nacc +5";
+
+    #[test]
+    fn breakpoints() {
+        let output = session(PROGRAM, "b 2\nc\nc\np\nd 2\nd 2\nq\n");
+
+        assert_eq!(
+            output,
+            "(dbg) breakpoint at 2\n\
+             (dbg) 0: nop +0  acc = 0\n\
+             1: acc +1  acc = 1\n\
+             (dbg) 2: jmp -2  acc = 1\n\
+             halted at 0: InfiniteLoop\n\
+             (dbg) pc = 0, acc = 1\n\
+             halted: InfiniteLoop\n\
+             (dbg) (dbg) no breakpoint at 2\n\
+             (dbg) "
+        );
+    }
+
+    #[test]
+    fn toggle_and_reset() {
+        let output = session(PROGRAM, "t 2\nt 1\nc\np\nr\np\nq\n");
+
+        assert_eq!(
+            output,
+            "(dbg) 2: nop -2\n\
+             (dbg) instruction 1 can't be toggled\n\
+             (dbg) 0: nop +0  acc = 0\n\
+             1: acc +1  acc = 1\n\
+             2: nop -2  acc = 1\n\
+             3: acc +5  acc = 6\n\
+             halted at 4: Terminated\n\
+             (dbg) pc = 4, acc = 6\n\
+             halted: Terminated\n\
+             (dbg) (dbg) pc = 0, acc = 0\n\
+             (dbg) "
+        );
+    }
+
+    #[test]
+    fn list() {
+        let output = session(PROGRAM, "b 3\ns 2\nl\nl 100\n");
+
+        assert_eq!(
+            output,
+            "(dbg) breakpoint at 3\n\
+             (dbg) 0: nop +0  acc = 0\n\
+             1: acc +1  acc = 1\n\
+             (dbg)       0: nop +0\n      \
+             1: acc +1\n\
+             >     2: jmp -2\n \
+             *    3: acc +5\n\
+             (dbg) (dbg) \n"
+        );
+    }
+
+    #[test]
+    fn unknown_commands_print_help() {
+        let output = session(PROGRAM, "bogus\nb x\n");
+
+        assert_eq!(output, format!("(dbg) {0}(dbg) {0}(dbg) \n", HELP));
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt,
+};
+
+use super::{Halt, Instruction, Machine};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Edit {
+    Flip(usize),
+    Operand(usize, i32),
+    Delete(usize),
+}
+
+impl Edit {
+    pub fn index(self) -> usize {
+        match self {
+            Self::Flip(i) | Self::Operand(i, _) | Self::Delete(i) => i,
+        }
+    }
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Flip(i) => write!(f, "flip {}", i),
+            Self::Operand(i, val) => write!(f, "set {} to {:+}", i, val),
+            Self::Delete(i) => write!(f, "delete {}", i),
+        }
+    }
+}
+
+pub struct RepairOptions {
+    pub max_edits: usize,
+    pub flips: bool,
+    pub operand_deltas: Vec<i32>,
+    pub deletions: bool,
+    pub max_executions: usize,
+}
+
+impl Default for RepairOptions {
+    fn default() -> Self {
+        Self {
+            max_edits: 1,
+            flips: true,
+            operand_deltas: Vec::new(),
+            deletions: false,
+            max_executions: 100_000,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Repair {
+    pub edits: Vec<Edit>,
+    pub accumulator: i32,
+}
+
+#[derive(Debug)]
+pub struct RepairSearch {
+    pub repairs: Vec<Repair>,
+    pub executions: usize,
+    pub complete: bool,
+}
+
+impl RepairSearch {
+    fn new(repairs: BTreeSet<(Vec<Edit>, i32)>, executions: usize, complete: bool) -> Self {
+        Self {
+            repairs: repairs
+                .into_iter()
+                .map(|(edits, accumulator)| Repair { edits, accumulator })
+                .collect(),
+            executions,
+            complete,
+        }
+    }
+}
+
+pub fn apply(program: &[Instruction], edits: &[Edit]) -> (Vec<Instruction>, Vec<usize>) {
+    let mut patched_program = Vec::new();
+    let mut origins = Vec::new();
+
+    for (i, &instruction) in program.iter().enumerate() {
+        let instruction = match edits.iter().find(|edit| edit.index() == i) {
+            None => instruction,
+            Some(Edit::Flip(_)) => instruction.flipped().unwrap_or(instruction),
+            Some(Edit::Operand(_, val)) => match instruction {
+                Instruction::Acc(_) => Instruction::Acc(*val),
+                Instruction::Jmp(_) => Instruction::Jmp(*val),
+                Instruction::Nop(_) => Instruction::Nop(*val),
+            },
+            Some(Edit::Delete(_)) => continue,
+        };
+
+        patched_program.push(instruction);
+        origins.push(i);
+    }
+
+    (patched_program, origins)
+}
+
+fn candidate_edits<'a>(
+    program: &'a [Instruction],
+    executed: &'a [usize],
+    options: &'a RepairOptions,
+) -> impl Iterator<Item = Edit> + 'a {
+    let on_path = executed.iter().flat_map(move |&i| {
+        let flip = program[i]
+            .flipped()
+            .filter(|_| options.flips)
+            .map(|_| Edit::Flip(i));
+
+        let operands = options
+            .operand_deltas
+            .iter()
+            .filter(|&&delta| delta != 0)
+            .map(move |&delta| match program[i] {
+                Instruction::Acc(val) | Instruction::Jmp(val) | Instruction::Nop(val) => {
+                    Edit::Operand(i, val + delta)
+                }
+            });
+
+        flip.into_iter().chain(operands)
+    });
+
+    // Deleting an instruction shifts every jump across it, so deletions off
+    // the executed path can still matter.
+    let deletions = (0..program.len())
+        .filter(move |_| options.deletions)
+        .map(Edit::Delete);
+
+    on_path.chain(deletions)
+}
+
+pub fn search(program: &[Instruction], options: &RepairOptions) -> RepairSearch {
+    let mut machine = Machine::new(program.to_vec());
+    if machine.run() == Halt::Terminated {
+        let repair = (Vec::new(), machine.accumulator());
+        return RepairSearch::new(Some(repair).into_iter().collect(), 1, true);
+    }
+
+    let mut executions = 1;
+    let mut explored = HashSet::new();
+    let mut frontier = vec![Vec::new()];
+
+    for _ in 0..options.max_edits {
+        let mut repairs = BTreeSet::new();
+        let mut next_frontier = Vec::new();
+
+        for edits in &frontier {
+            let (patched_program, origins) = apply(program, edits);
+            let mut executed = Vec::new();
+            Machine::new(patched_program).run_with(|step| executed.push(origins[step.index]));
+
+            for edit in candidate_edits(program, &executed, options) {
+                if edits.iter().any(|e| e.index() == edit.index()) {
+                    continue;
+                }
+
+                let mut new_edits = edits.clone();
+                new_edits.push(edit);
+                new_edits.sort();
+
+                if !explored.insert(new_edits.clone()) {
+                    continue;
+                }
+
+                if executions >= options.max_executions {
+                    return RepairSearch::new(repairs, executions, false);
+                }
+
+                executions += 1;
+                let mut machine = Machine::new(apply(program, &new_edits).0);
+
+                if machine.run() == Halt::Terminated {
+                    repairs.insert((new_edits, machine.accumulator()));
+                } else {
+                    next_frontier.push(new_edits);
+                }
+            }
+        }
+
+        if !repairs.is_empty() {
+            return RepairSearch::new(repairs, executions, true);
+        }
+
+        frontier = next_frontier;
+    }
+
+    RepairSearch::new(BTreeSet::new(), executions, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::asm::assemble;
+
+    #[test]
+    fn terminating_program_needs_no_edits() {
+        let program = assemble("acc +1\nacc +2").unwrap();
+        let search = search(&program, &RepairOptions::default());
+
+        assert_eq!(
+            search.repairs,
+            vec![Repair {
+                edits: Vec::new(),
+                accumulator: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn operand_deltas_apply_to_every_instruction() {
+        let program = assemble("acc +1\nnop +0\njmp -2").unwrap();
+        let options = RepairOptions {
+            max_edits: 2,
+            flips: false,
+            operand_deltas: vec![3],
+            ..RepairOptions::default()
+        };
+
+        let search = search(&program, &options);
+        assert_eq!(
+            search.repairs,
+            vec![Repair {
+                edits: vec![Edit::Operand(2, 1)],
+                accumulator: 1
+            }]
+        );
+
+        let candidates = candidate_edits(&program, &[0, 1, 2], &options).collect::<Vec<_>>();
+        assert_eq!(
+            candidates,
+            vec![
+                Edit::Operand(0, 4),
+                Edit::Operand(1, 3),
+                Edit::Operand(2, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn execution_budget_stops_the_search() {
+        let program = assemble("nop +0\nacc +1\njmp -2").unwrap();
+
+        for &max_executions in &[0, 1, 2] {
+            let options = RepairOptions {
+                max_executions,
+                ..RepairOptions::default()
+            };
+
+            let search = search(&program, &options);
+            assert!(!search.complete, "{}", max_executions);
+            assert!(search.repairs.is_empty(), "{}", max_executions);
+            assert_eq!(search.executions, max_executions.max(1));
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::fmt;
+
+use super::{Halt, Machine, Step};
+
+pub struct Table<'a>(pub &'a [Step]);
+
+impl fmt::Display for Table<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>6}  {:>5}  {:<10}  {:>11}",
+            "step", "index", "instr", "accumulator"
+        )?;
+
+        for (i, step) in self.0.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>6}  {:>5}  {:<10}  {:>11}",
+                i,
+                step.index,
+                step.instruction.to_string(),
+                step.accumulator
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Trace {
+    pub steps: Vec<Step>,
+    pub halt: Halt,
+    pub halted_at: i32,
+}
+
+impl Trace {
+    pub fn record(machine: &mut Machine) -> Self {
+        let mut steps = Vec::new();
+        let halt = machine.run_with(|step| steps.push(*step));
+
+        Self {
+            steps,
+            halt,
+            halted_at: machine.program_counter(),
+        }
+    }
+
+    pub fn cycle(&self) -> Option<&[Step]> {
+        if self.halt != Halt::InfiniteLoop {
+            return None;
+        }
+
+        let start = self
+            .steps
+            .iter()
+            .position(|step| step.index as i32 == self.halted_at)?;
+
+        Some(&self.steps[start..])
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Table(&self.steps))?;
+        writeln!(f, "halted at {}: {:?}", self.halted_at, self.halt)?;
+
+        if let Some(cycle) = self.cycle() {
+            let indices = cycle
+                .iter()
+                .map(|step| step.index.to_string())
+                .collect::<Vec<_>>();
+
+            writeln!(f)?;
+            writeln!(f, "loop: {} -> {}", indices.join(" -> "), self.halted_at)?;
+            write!(f, "{}", Table(cycle))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use std::{cmp::Ordering, fmt, iter::FromIterator, ops::RangeInclusive, str::FromStr};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    ranges: Vec<(u32, u32)>,
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn len(&self) -> u64 {
+        self.ranges
+            .iter()
+            .map(|&(start, end)| u64::from(end - start) + 1)
+            .sum()
+    }
+
+    pub fn ranges(&self) -> impl Iterator<Item = RangeInclusive<u32>> + '_ {
+        self.ranges.iter().map(|&(start, end)| start..=end)
+    }
+
+    pub fn contains(&self, value: u32) -> bool {
+        self.ranges
+            .binary_search_by(|&(start, end)| {
+                if end < value {
+                    Ordering::Less
+                } else if start > value {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+
+    pub fn insert(&mut self, range: RangeInclusive<u32>) {
+        *self = self.ranges().chain(Some(range)).collect();
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.ranges().chain(other.ranges()).collect()
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut ranges = Vec::new();
+        let (mut a, mut b) = (
+            self.ranges.iter().peekable(),
+            other.ranges.iter().peekable(),
+        );
+
+        while let (Some(&&(a_start, a_end)), Some(&&(b_start, b_end))) = (a.peek(), b.peek()) {
+            let (start, end) = (a_start.max(b_start), a_end.min(b_end));
+            if start <= end {
+                ranges.push((start, end));
+            }
+
+            if a_end < b_end {
+                a.next();
+            } else {
+                b.next();
+            }
+        }
+
+        Self { ranges }
+    }
+}
+
+impl FromIterator<RangeInclusive<u32>> for IntervalSet {
+    fn from_iter<I: IntoIterator<Item = RangeInclusive<u32>>>(iter: I) -> Self {
+        let mut sorted = iter
+            .into_iter()
+            .filter(|range| !range.is_empty())
+            .map(RangeInclusive::into_inner)
+            .collect::<Vec<_>>();
+        sorted.sort_unstable();
+
+        let mut ranges: Vec<(u32, u32)> = Vec::with_capacity(sorted.len());
+        for (start, end) in sorted {
+            match ranges.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => ranges.push((start, end)),
+            }
+        }
+
+        Self { ranges }
+    }
+}
+
+impl FromStr for IntervalSet {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(" or ")
+            .map(|range| {
+                let mut bounds = range.splitn(2, '-');
+                let start = bounds.next().ok_or(())?.parse().map_err(|_| ())?;
+                let end = bounds.next().ok_or(())?.parse().map_err(|_| ())?;
+                Ok(start..=end)
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for IntervalSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, &(start, end)) in self.ranges.iter().enumerate() {
+            if i > 0 {
+                write!(f, " or ")?;
+            }
+            write!(f, "{}-{}", start, end)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[RangeInclusive<u32>]) -> IntervalSet {
+        ranges.iter().cloned().collect()
+    }
+
+    #[test]
+    fn merges_overlapping_and_adjacent_ranges() {
+        assert_eq!(set(&[5..=7, 1..=3, 4..=4]).to_string(), "1-7");
+        assert_eq!(set(&[1..=10, 2..=3, 12..=15]).to_string(), "1-10 or 12-15");
+        assert_eq!(set(&[RangeInclusive::new(3, 2), 0..=0]).to_string(), "0-0");
+        assert_eq!(set(&[0..=5, 6..=u32::MAX]).len(), 1 << 32);
+
+        let mut ranges = set(&[1..=2, 6..=8]);
+        ranges.insert(3..=5);
+        assert_eq!(ranges, set(&[1..=8]));
+    }
+
+    #[test]
+    fn contains_at_range_boundaries() {
+        let ranges = set(&[1..=3, 5..=7, 10..=10]);
+
+        for value in &[1, 3, 5, 7, 10] {
+            assert!(ranges.contains(*value), "{}", value);
+        }
+        for value in &[0, 4, 8, 9, 11, u32::MAX] {
+            assert!(!ranges.contains(*value), "{}", value);
+        }
+        assert!(!IntervalSet::new().contains(0));
+    }
+
+    #[test]
+    fn union_and_intersection() {
+        let a = set(&[1..=5, 10..=20, 30..=30]);
+        let b = set(&[4..=12, 20..=25, 31..=40]);
+
+        assert_eq!(a.union(&b), set(&[1..=25, 30..=40]));
+        assert_eq!(a.intersection(&b), set(&[4..=5, 10..=12, 20..=20]));
+        assert_eq!(a.intersection(&IntervalSet::new()), IntervalSet::new());
+        assert_eq!(a.intersection(&a), a);
+    }
+
+    #[test]
+    fn parses_and_displays_rule_ranges() {
+        let ranges = "26-744 or 765-967".parse::<IntervalSet>().unwrap();
+
+        assert_eq!(ranges.to_string(), "26-744 or 765-967");
+        assert_eq!("1-3 or x".parse::<IntervalSet>(), Err(()));
+    }
+}
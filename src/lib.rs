@@ -1,3 +1,7 @@
+pub mod bigint;
+pub mod console;
+pub mod interval_set;
+
 use std::ops::{Add, AddAssign, Mul};
 
 #[derive(Clone, Copy, Debug, Default)]